@@ -1 +1,2 @@
+pub mod pacing;
 pub mod util;
@@ -0,0 +1,211 @@
+use std::time::{Duration, Instant};
+
+use bevy::ecs::system::Resource;
+use bevy::render::extract_resource::ExtractResource;
+
+/// The time between two vblanks, the 3ds screens refresh at about 59.83 Hz.
+pub const VBLANK_PERIOD: Duration = Duration::from_nanos(16_713_680);
+
+/// How often frames are presented.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FramePacingMode {
+    /// Present on every vblank.
+    #[default]
+    VSync60,
+    /// Present on every other vblank, for a stable 30 fps.
+    VSync30,
+    /// Present as soon as the frame is done, without waiting for a vblank.
+    Uncapped,
+}
+
+impl FramePacingMode {
+    /// The number of vblanks a frame is shown for, `0` when uncapped.
+    pub fn vblanks_per_frame(self) -> u32 {
+        match self {
+            FramePacingMode::VSync60 => 1,
+            FramePacingMode::VSync30 => 2,
+            FramePacingMode::Uncapped => 0,
+        }
+    }
+}
+
+/// Controls the frame rate, insert or modify this in the main world.
+#[derive(Resource, ExtractResource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FramePacing3ds {
+    pub mode: FramePacingMode,
+}
+
+/// Decides how many vblanks to wait for each frame and smooths the frame timestamps
+/// reported to [`Time`](bevy::time::Time).
+///
+/// When vsynced, frames are presented on vblanks so the real frame times are a whole
+/// number of [`VBLANK_PERIOD`]s plus scheduling jitter. The reported timestamps are
+/// snapped to that grid, so `Time` deltas stay constant while the game keeps up.
+///
+/// Timestamps are [`Duration`]s since [`FramePacer3ds::epoch`], so the logic can be
+/// driven by any clock.
+#[derive(Resource, Debug, Clone)]
+pub struct FramePacer3ds {
+    epoch: Instant,
+    last_present: Option<Duration>,
+    smoothed: Option<Duration>,
+    jitter: Duration,
+}
+
+impl Default for FramePacer3ds {
+    fn default() -> Self {
+        Self {
+            epoch: Instant::now(),
+            last_present: None,
+            smoothed: None,
+            jitter: Duration::ZERO,
+        }
+    }
+}
+
+impl FramePacer3ds {
+    /// The instant timestamps are measured from.
+    pub fn epoch(&self) -> Instant {
+        self.epoch
+    }
+
+    /// The current time on the real clock.
+    pub fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+
+    /// The smoothed timestamp of the last presented frame.
+    pub fn last_presented(&self) -> Option<Instant> {
+        self.smoothed.map(|smoothed| self.epoch + smoothed)
+    }
+
+    /// A running average of how far real frame times are from a whole number of vblanks.
+    pub fn jitter(&self) -> Duration {
+        self.jitter
+    }
+
+    /// The number of vblanks to wait for before presenting, given that the frame is
+    /// ready at `now`.
+    ///
+    /// Vblanks that passed while the frame was being built count towards the wait, but
+    /// a vsynced frame always waits for at least one.
+    pub fn vblanks_to_wait(&self, mode: FramePacingMode, now: Duration) -> u32 {
+        let per_frame = mode.vblanks_per_frame();
+        if per_frame == 0 {
+            return 0;
+        }
+        let Some(last_present) = self.last_present else {
+            return 1;
+        };
+        let passed = now.saturating_sub(last_present).as_nanos() / VBLANK_PERIOD.as_nanos();
+        per_frame.saturating_sub(passed as u32).max(1)
+    }
+
+    /// Records that a frame was presented at `now`, returning the smoothed timestamp to
+    /// report for it.
+    pub fn present(&mut self, mode: FramePacingMode, now: Duration) -> Duration {
+        let last_present = self.last_present.replace(now);
+        let (Some(last_present), Some(smoothed), false) = (
+            last_present,
+            self.smoothed,
+            mode == FramePacingMode::Uncapped,
+        ) else {
+            self.smoothed = Some(now);
+            return now;
+        };
+
+        let interval = now.saturating_sub(last_present);
+        let vblanks = (interval.as_secs_f64() / VBLANK_PERIOD.as_secs_f64())
+            .round()
+            .max(1.0) as u32;
+        let snapped = VBLANK_PERIOD * vblanks;
+        self.jitter = (self.jitter * 7 + interval.abs_diff(snapped)) / 8;
+
+        let mut next = smoothed + snapped;
+        // don't let the smoothed clock drift away from the real one
+        if next.abs_diff(now) > VBLANK_PERIOD {
+            next = now;
+        }
+        self.smoothed = Some(next);
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn waits_for_the_rest_of_the_frame() {
+        let mut pacer = FramePacer3ds::default();
+        assert_eq!(
+            pacer.vblanks_to_wait(FramePacingMode::VSync30, Duration::ZERO),
+            1
+        );
+        assert_eq!(
+            pacer.vblanks_to_wait(FramePacingMode::Uncapped, Duration::ZERO),
+            0
+        );
+
+        pacer.present(FramePacingMode::VSync30, Duration::ZERO);
+        assert_eq!(pacer.vblanks_to_wait(FramePacingMode::VSync30, 5 * MS), 2);
+        // a vblank passed while the frame was built
+        assert_eq!(pacer.vblanks_to_wait(FramePacingMode::VSync30, 20 * MS), 1);
+        // late frames still wait for a vblank when vsynced
+        assert_eq!(pacer.vblanks_to_wait(FramePacingMode::VSync30, 50 * MS), 1);
+        assert_eq!(pacer.vblanks_to_wait(FramePacingMode::VSync60, 50 * MS), 1);
+        assert_eq!(pacer.vblanks_to_wait(FramePacingMode::Uncapped, 5 * MS), 0);
+    }
+
+    #[test]
+    fn timestamps_snap_to_vblanks() {
+        let mut pacer = FramePacer3ds::default();
+        let start = Duration::from_secs(1);
+        assert_eq!(pacer.present(FramePacingMode::VSync60, start), start);
+
+        // presents jittering around every vblank report exactly one vblank apart
+        let mut clock = start;
+        for (i, jitter) in [300_000, 0, 500_000, 100_000].into_iter().enumerate() {
+            clock = start + VBLANK_PERIOD * (i as u32 + 1) + Duration::from_nanos(jitter);
+            let reported = pacer.present(FramePacingMode::VSync60, clock);
+            assert_eq!(reported, start + VBLANK_PERIOD * (i as u32 + 1));
+        }
+        assert!(pacer.jitter() > Duration::ZERO && pacer.jitter() < MS);
+        assert_eq!(
+            pacer.last_presented(),
+            Some(pacer.epoch() + start + VBLANK_PERIOD * 4)
+        );
+
+        // a dropped frame counts as two vblanks
+        let reported = pacer.present(FramePacingMode::VSync60, clock + VBLANK_PERIOD * 2);
+        assert_eq!(reported, start + VBLANK_PERIOD * 6);
+    }
+
+    #[test]
+    fn timestamps_follow_the_clock_when_they_drift() {
+        let mut pacer = FramePacer3ds::default();
+        pacer.present(FramePacingMode::VSync60, Duration::ZERO);
+
+        // every frame is 40% of a vblank late, which rounds back down to one vblank
+        let late = VBLANK_PERIOD * 14 / 10;
+        let mut clock = Duration::ZERO;
+        let mut reported = Duration::ZERO;
+        for _ in 0..5 {
+            clock += late;
+            reported = pacer.present(FramePacingMode::VSync60, clock);
+            assert!(reported.abs_diff(clock) <= VBLANK_PERIOD);
+        }
+        assert!(reported < clock);
+    }
+
+    #[test]
+    fn uncapped_reports_the_real_clock() {
+        let mut pacer = FramePacer3ds::default();
+        for now in [0, 7, 19, 20] {
+            let now = MS * now;
+            assert_eq!(pacer.present(FramePacingMode::Uncapped, now), now);
+        }
+    }
+}
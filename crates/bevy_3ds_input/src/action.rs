@@ -0,0 +1,583 @@
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use bevy::app::{App, Plugin, PreUpdate};
+use bevy::ecs::schedule::IntoSystemConfigs;
+use bevy::ecs::system::{Res, ResMut, Resource};
+use bevy::input::{Axis, Input, InputSystem};
+use bevy::utils::{HashMap, HashSet};
+
+use crate::axis::{Axis3ds, Axis3dsType};
+use crate::button::{Button3ds, Button3dsType};
+use crate::timing::Chord3ds;
+
+/// A user defined action, usually a fieldless enum.
+pub trait Action3ds: Copy + Eq + Hash + Send + Sync + 'static {}
+
+impl<T: Copy + Eq + Hash + Send + Sync + 'static> Action3ds for T {}
+
+/// Which way along an axis a [`Binding3ds::Axis`] listens to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum AxisDirection {
+    /// Values above zero, e.g. right or up on the circle pad.
+    Positive,
+    /// Values below zero, e.g. left or down on the circle pad.
+    Negative,
+}
+
+/// An input an action can be bound to.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum Binding3ds {
+    /// A single button.
+    Button(Button3dsType),
+    /// Several buttons that all have to be held, e.g. L+R+Select.
+    Chord(Chord3ds),
+    /// One direction of an axis, pressed once the axis goes past `threshold`.
+    Axis {
+        /// The axis to read.
+        axis: Axis3dsType,
+        /// The half of the axis the binding listens to.
+        direction: AxisDirection,
+        /// How far along `direction` the axis has to be to press the binding, in `[0, 1]`.
+        threshold: f32,
+    },
+}
+
+impl Binding3ds {
+    /// Creates a [`Binding3ds::Chord`].
+    pub fn chord(buttons: impl IntoIterator<Item = Button3dsType>) -> Self {
+        Self::Chord(Chord3ds::new(buttons))
+    }
+
+    /// Creates a [`Binding3ds::Axis`].
+    pub fn axis(axis: Axis3dsType, direction: AxisDirection, threshold: f32) -> Self {
+        Self::Axis {
+            axis,
+            direction,
+            threshold,
+        }
+    }
+
+    /// The value of the binding in `[0, 1]` and whether it is pressed.
+    fn evaluate(&self, buttons: &Input<Button3ds>, axes: &Axis<Axis3ds>) -> (f32, bool) {
+        let pressed = |b: &Button3dsType| buttons.pressed(Button3ds::new(*b));
+        match self {
+            Binding3ds::Button(button) => {
+                let p = pressed(button);
+                (if p { 1.0 } else { 0.0 }, p)
+            }
+            Binding3ds::Chord(chord) => {
+                let p = chord.pressed(buttons);
+                (if p { 1.0 } else { 0.0 }, p)
+            }
+            Binding3ds::Axis {
+                axis,
+                direction,
+                threshold,
+            } => {
+                let raw = axes.get(Axis3ds::new(*axis)).unwrap_or(0.0);
+                let value = match direction {
+                    AxisDirection::Positive => raw,
+                    AxisDirection::Negative => -raw,
+                }
+                .clamp(0.0, 1.0);
+                (value, value > 0.0 && value >= *threshold)
+            }
+        }
+    }
+}
+
+impl From<Button3dsType> for Binding3ds {
+    fn from(value: Button3dsType) -> Self {
+        Self::Button(value)
+    }
+}
+
+impl From<Chord3ds> for Binding3ds {
+    fn from(value: Chord3ds) -> Self {
+        Self::Chord(value)
+    }
+}
+
+/// Binds actions of type `A` to 3ds inputs.
+///
+/// ## Usage
+///
+/// Bindings can be changed at any time, the [`ActionState`] picks them up on the next
+/// update. With the `serialize` feature the map can be saved with [`ActionMap::to_bytes`]
+/// and loaded with [`ActionMap::from_bytes`].
+#[derive(Resource, Debug, Clone)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "A: serde::Serialize + serde::de::DeserializeOwned")
+)]
+pub struct ActionMap<A: Action3ds> {
+    bindings: HashMap<A, Vec<Binding3ds>>,
+}
+
+impl<A: Action3ds> Default for ActionMap<A> {
+    fn default() -> Self {
+        Self {
+            bindings: HashMap::default(),
+        }
+    }
+}
+
+impl<A: Action3ds> ActionMap<A> {
+    /// Adds a binding to `action`, keeping its existing bindings.
+    pub fn bind(&mut self, action: A, binding: impl Into<Binding3ds>) -> &mut Self {
+        let binding = binding.into();
+        let bindings = self.bindings.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        self
+    }
+
+    /// Replaces all the bindings of `action`.
+    pub fn rebind(
+        &mut self,
+        action: A,
+        bindings: impl IntoIterator<Item = Binding3ds>,
+    ) -> &mut Self {
+        self.bindings.insert(action, bindings.into_iter().collect());
+        self
+    }
+
+    /// Removes a single binding from `action`.
+    pub fn unbind(&mut self, action: A, binding: &Binding3ds) -> &mut Self {
+        if let Some(bindings) = self.bindings.get_mut(&action) {
+            bindings.retain(|b| b != binding);
+        }
+        self
+    }
+
+    /// Removes all the bindings of `action`.
+    pub fn clear(&mut self, action: A) -> &mut Self {
+        self.bindings.remove(&action);
+        self
+    }
+
+    /// The bindings of `action`.
+    pub fn bindings(&self, action: A) -> &[Binding3ds] {
+        self.bindings.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Iterates over every action with at least one binding.
+    pub fn actions(&self) -> impl Iterator<Item = A> + '_ {
+        self.bindings
+            .iter()
+            .filter(|(_, b)| !b.is_empty())
+            .map(|(a, _)| *a)
+    }
+}
+
+#[cfg(feature = "serialize")]
+impl<A: Action3ds + serde::Serialize + serde::de::DeserializeOwned> ActionMap<A> {
+    /// Serializes the bindings, e.g. to be written to save data.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, bincode::Error> {
+        bincode::serialize(self)
+    }
+
+    /// Deserializes bindings written with [`ActionMap::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, bincode::Error> {
+        bincode::deserialize(bytes)
+    }
+}
+
+/// The state of the actions of type `A`.
+///
+/// ## Updating
+///
+/// This resource is updated from the [`ActionMap`] inside of the [`action_state_system`].
+#[derive(Resource, Debug, Clone)]
+pub struct ActionState<A: Action3ds> {
+    pressed: HashSet<A>,
+    just_pressed: HashSet<A>,
+    just_released: HashSet<A>,
+    values: HashMap<A, f32>,
+}
+
+impl<A: Action3ds> Default for ActionState<A> {
+    fn default() -> Self {
+        Self {
+            pressed: HashSet::default(),
+            just_pressed: HashSet::default(),
+            just_released: HashSet::default(),
+            values: HashMap::default(),
+        }
+    }
+}
+
+impl<A: Action3ds> ActionState<A> {
+    /// Whether any binding of `action` is held.
+    pub fn pressed(&self, action: A) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    /// Whether `action` started being held this frame.
+    pub fn just_pressed(&self, action: A) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    /// Whether `action` stopped being held this frame.
+    pub fn just_released(&self, action: A) -> bool {
+        self.just_released.contains(&action)
+    }
+
+    /// The strongest value of any binding of `action`, in `[0, 1]`.
+    ///
+    /// Buttons are `1.0` when held, axis bindings report how far along their direction the axis is.
+    pub fn value(&self, action: A) -> f32 {
+        self.values.get(&action).copied().unwrap_or(0.0)
+    }
+
+    /// Iterates over every held action.
+    pub fn get_pressed(&self) -> impl Iterator<Item = &A> {
+        self.pressed.iter()
+    }
+
+    /// Recomputes the state of every action from the current inputs.
+    pub fn update(&mut self, map: &ActionMap<A>, buttons: &Input<Button3ds>, axes: &Axis<Axis3ds>) {
+        let previous = std::mem::take(&mut self.pressed);
+        self.just_pressed.clear();
+        self.just_released.clear();
+        self.values.clear();
+
+        for (action, bindings) in &map.bindings {
+            let (value, pressed) = bindings
+                .iter()
+                .map(|b| b.evaluate(buttons, axes))
+                .fold((0.0f32, false), |(v, p), (bv, bp)| (v.max(bv), p || bp));
+            if value > 0.0 {
+                self.values.insert(*action, value);
+            }
+            if pressed {
+                self.pressed.insert(*action);
+                if !previous.contains(action) {
+                    self.just_pressed.insert(*action);
+                }
+            }
+        }
+        self.just_released
+            .extend(previous.difference(&self.pressed).copied());
+    }
+}
+
+/// Updates the [`ActionState`] from the [`ActionMap`] and the 3ds inputs.
+pub fn action_state_system<A: Action3ds>(
+    map: Res<ActionMap<A>>,
+    buttons: Res<Input<Button3ds>>,
+    axes: Res<Axis<Axis3ds>>,
+    mut state: ResMut<ActionState<A>>,
+) {
+    state.update(&map, &buttons, &axes);
+}
+
+/// Adds an [`ActionMap`] and [`ActionState`] for actions of type `A`.
+///
+/// Requires the [`InputPlugin`](crate::InputPlugin).
+pub struct ActionMapPlugin<A>(PhantomData<A>);
+
+impl<A> Default for ActionMapPlugin<A> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<A: Action3ds> Plugin for ActionMapPlugin<A> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionMap<A>>()
+            .init_resource::<ActionState<A>>()
+            .add_systems(PreUpdate, action_state_system::<A>.after(InputSystem));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::Events;
+    use bevy::input::ButtonState;
+
+    use super::*;
+    use crate::event::{CtruButtonChangedEvent, Event3ds};
+    use crate::InputPlugin;
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    #[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+    enum Action {
+        Jump,
+        Run,
+        Menu,
+    }
+
+    #[derive(Default)]
+    struct Inputs {
+        buttons: Input<Button3ds>,
+        axes: Axis<Axis3ds>,
+    }
+
+    impl Inputs {
+        fn press(&mut self, button: Button3dsType) {
+            self.buttons.press(Button3ds::new(button));
+        }
+
+        fn release(&mut self, button: Button3dsType) {
+            self.buttons.release(Button3ds::new(button));
+        }
+
+        fn set(&mut self, axis: Axis3dsType, value: f32) {
+            self.axes.set(Axis3ds::new(axis), value);
+        }
+
+        /// Updates `state` and clears the just pressed state, like a frame of the app.
+        fn update(&mut self, state: &mut ActionState<Action>, map: &ActionMap<Action>) {
+            state.update(map, &self.buttons, &self.axes);
+            self.buttons.clear();
+        }
+    }
+
+    #[test]
+    fn actions_are_just_pressed_and_released_once() {
+        let mut map = ActionMap::default();
+        map.bind(Action::Jump, Button3dsType::A)
+            .bind(Action::Jump, Button3dsType::B);
+        let mut state = ActionState::default();
+        let mut inputs = Inputs::default();
+
+        inputs.press(Button3dsType::A);
+        inputs.update(&mut state, &map);
+        assert!(state.pressed(Action::Jump));
+        assert!(state.just_pressed(Action::Jump));
+        assert_eq!(state.value(Action::Jump), 1.0);
+
+        // a second binding going down doesn't press the action again
+        inputs.press(Button3dsType::B);
+        inputs.update(&mut state, &map);
+        assert!(state.pressed(Action::Jump));
+        assert!(!state.just_pressed(Action::Jump));
+
+        inputs.release(Button3dsType::A);
+        inputs.update(&mut state, &map);
+        assert!(state.pressed(Action::Jump));
+        assert!(!state.just_released(Action::Jump));
+
+        inputs.release(Button3dsType::B);
+        inputs.update(&mut state, &map);
+        assert!(!state.pressed(Action::Jump));
+        assert!(state.just_released(Action::Jump));
+        assert_eq!(state.value(Action::Jump), 0.0);
+
+        inputs.update(&mut state, &map);
+        assert!(!state.just_released(Action::Jump));
+        assert_eq!(state.get_pressed().count(), 0);
+    }
+
+    #[test]
+    fn bindings_can_be_changed() {
+        let mut map = ActionMap::default();
+        map.bind(Action::Run, Button3dsType::Y)
+            .bind(Action::Run, Button3dsType::Y)
+            .bind(Action::Run, Button3dsType::X);
+        assert_eq!(
+            map.bindings(Action::Run),
+            &[
+                Binding3ds::Button(Button3dsType::Y),
+                Binding3ds::Button(Button3dsType::X)
+            ]
+        );
+
+        map.unbind(Action::Run, &Binding3ds::Button(Button3dsType::Y));
+        assert_eq!(
+            map.bindings(Action::Run),
+            &[Binding3ds::Button(Button3dsType::X)]
+        );
+
+        map.rebind(Action::Run, [Binding3ds::Button(Button3dsType::R)]);
+        assert_eq!(
+            map.bindings(Action::Run),
+            &[Binding3ds::Button(Button3dsType::R)]
+        );
+
+        // actions without bindings aren't listed
+        map.unbind(Action::Run, &Binding3ds::Button(Button3dsType::R));
+        map.bind(Action::Menu, Button3dsType::Start);
+        assert_eq!(map.actions().collect::<Vec<_>>(), vec![Action::Menu]);
+        map.clear(Action::Menu);
+        assert!(map.bindings(Action::Menu).is_empty());
+        assert_eq!(map.actions().count(), 0);
+    }
+
+    #[test]
+    fn rebinding_releases_the_old_binding() {
+        let mut map = ActionMap::default();
+        map.bind(Action::Jump, Button3dsType::A);
+        let mut state = ActionState::default();
+        let mut inputs = Inputs::default();
+
+        inputs.press(Button3dsType::A);
+        inputs.update(&mut state, &map);
+        assert!(state.pressed(Action::Jump));
+
+        map.rebind(Action::Jump, [Binding3ds::Button(Button3dsType::B)]);
+        inputs.update(&mut state, &map);
+        assert!(state.just_released(Action::Jump));
+
+        inputs.press(Button3dsType::B);
+        inputs.update(&mut state, &map);
+        assert!(state.just_pressed(Action::Jump));
+    }
+
+    #[test]
+    fn axes_press_past_the_threshold_in_their_direction() {
+        let mut map = ActionMap::default();
+        map.bind(
+            Action::Run,
+            Binding3ds::axis(Axis3dsType::CPadX, AxisDirection::Positive, 0.5),
+        )
+        .bind(
+            Action::Jump,
+            Binding3ds::axis(Axis3dsType::CPadY, AxisDirection::Negative, 0.5),
+        );
+        let mut state = ActionState::default();
+        let mut inputs = Inputs::default();
+
+        inputs.set(Axis3dsType::CPadX, 0.4);
+        inputs.set(Axis3dsType::CPadY, -0.4);
+        inputs.update(&mut state, &map);
+        assert!(!state.pressed(Action::Run));
+        assert!(!state.pressed(Action::Jump));
+        assert_eq!(state.value(Action::Run), 0.4);
+        assert_eq!(state.value(Action::Jump), 0.4);
+
+        inputs.set(Axis3dsType::CPadX, 0.5);
+        inputs.set(Axis3dsType::CPadY, -0.5);
+        inputs.update(&mut state, &map);
+        assert!(state.just_pressed(Action::Run));
+        assert!(state.just_pressed(Action::Jump));
+
+        // the other direction doesn't count and values are capped at 1
+        inputs.set(Axis3dsType::CPadX, -1.0);
+        inputs.set(Axis3dsType::CPadY, -2.0);
+        inputs.update(&mut state, &map);
+        assert!(state.just_released(Action::Run));
+        assert_eq!(state.value(Action::Run), 0.0);
+        assert!(state.pressed(Action::Jump));
+        assert_eq!(state.value(Action::Jump), 1.0);
+    }
+
+    #[test]
+    fn resting_axes_never_press() {
+        let mut map = ActionMap::default();
+        map.bind(
+            Action::Run,
+            Binding3ds::axis(Axis3dsType::CPadX, AxisDirection::Positive, 0.0),
+        );
+        let mut state = ActionState::default();
+        let mut inputs = Inputs::default();
+
+        // an axis that hasn't been read yet
+        inputs.update(&mut state, &map);
+        assert!(!state.pressed(Action::Run));
+
+        inputs.set(Axis3dsType::CPadX, 0.0);
+        inputs.update(&mut state, &map);
+        assert!(!state.pressed(Action::Run));
+    }
+
+    #[test]
+    fn chords_need_every_button() {
+        let mut map = ActionMap::default();
+        map.bind(
+            Action::Menu,
+            Binding3ds::chord([Button3dsType::L, Button3dsType::R, Button3dsType::Select]),
+        );
+        let mut state = ActionState::default();
+        let mut inputs = Inputs::default();
+
+        inputs.press(Button3dsType::L);
+        inputs.press(Button3dsType::R);
+        inputs.update(&mut state, &map);
+        assert!(!state.pressed(Action::Menu));
+        assert_eq!(state.value(Action::Menu), 0.0);
+
+        inputs.press(Button3dsType::Select);
+        inputs.update(&mut state, &map);
+        assert!(state.just_pressed(Action::Menu));
+        assert_eq!(state.value(Action::Menu), 1.0);
+
+        inputs.release(Button3dsType::L);
+        inputs.update(&mut state, &map);
+        assert!(state.just_released(Action::Menu));
+    }
+
+    #[cfg(feature = "serialize")]
+    #[test]
+    fn maps_round_trip_through_bytes() {
+        let mut map = ActionMap::default();
+        map.bind(Action::Jump, Button3dsType::A)
+            .bind(
+                Action::Menu,
+                Binding3ds::chord([Button3dsType::L, Button3dsType::R]),
+            )
+            .bind(
+                Action::Run,
+                Binding3ds::axis(Axis3dsType::CPadX, AxisDirection::Negative, 0.25),
+            );
+
+        let bytes = map.to_bytes().unwrap();
+        let loaded = ActionMap::<Action>::from_bytes(&bytes).unwrap();
+        for action in [Action::Jump, Action::Run, Action::Menu] {
+            assert_eq!(loaded.bindings(action), map.bindings(action));
+        }
+
+        assert!(ActionMap::<Action>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(ActionMap::<Action>::from_bytes(&[0xff; 16]).is_err());
+    }
+
+    fn send(app: &mut App, button_type: Button3dsType, state: ButtonState) {
+        app.world
+            .resource_mut::<Events<Event3ds>>()
+            .send(CtruButtonChangedEvent::new(button_type, state).into());
+        app.update();
+    }
+
+    #[test]
+    fn the_plugin_updates_the_state_from_input_events() {
+        let mut app = App::new();
+        app.add_plugins((InputPlugin, ActionMapPlugin::<Action>::default()));
+        app.world
+            .resource_mut::<ActionMap<Action>>()
+            .bind(Action::Jump, Button3dsType::A);
+
+        send(&mut app, Button3dsType::A, ButtonState::Pressed);
+        let state = app.world.resource::<ActionState<Action>>();
+        assert!(state.just_pressed(Action::Jump));
+
+        app.update();
+        let state = app.world.resource::<ActionState<Action>>();
+        assert!(state.pressed(Action::Jump));
+        assert!(!state.just_pressed(Action::Jump));
+
+        // bindings changed between updates are used on the next one
+        app.world
+            .resource_mut::<ActionMap<Action>>()
+            .rebind(Action::Jump, [Binding3ds::Button(Button3dsType::B)]);
+        app.update();
+        let state = app.world.resource::<ActionState<Action>>();
+        assert!(state.just_released(Action::Jump));
+
+        send(&mut app, Button3dsType::B, ButtonState::Pressed);
+        assert!(app
+            .world
+            .resource::<ActionState<Action>>()
+            .just_pressed(Action::Jump));
+        send(&mut app, Button3dsType::B, ButtonState::Released);
+        assert!(app
+            .world
+            .resource::<ActionState<Action>>()
+            .just_released(Action::Jump));
+    }
+}
@@ -1,4 +1,6 @@
 use bevy::reflect::Reflect;
+#[cfg(feature = "serialize")]
+use bevy::reflect::{ReflectDeserialize, ReflectSerialize};
 
 /// An axis for a stick on the 3ds
 /// ## Usage
@@ -27,10 +29,10 @@ impl Axis3ds {
     /// # Examples
     ///
     /// ```
-    /// # use bevy_3ds::input::axis::{Axis3ds, Axis3dsType};
+    /// # use bevy_3ds_input::axis::{Axis3ds, Axis3dsType};
     /// #
-    /// let 3ds_axis = Axis3ds::new(
-    ///     Axis3dsType::LeftStickX,
+    /// let axis_3ds = Axis3ds::new(
+    ///     Axis3dsType::CPadX,
     /// );
     /// ```
     pub fn new(axis_type: Axis3dsType) -> Self {
@@ -65,4 +67,7 @@ pub enum Axis3dsType {
 
     // volume slider
     Volume,
+
+    // 3d depth slider, from 0.0 (3d off) to 1.0
+    Slider3d,
 }
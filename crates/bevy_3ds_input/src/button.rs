@@ -1,4 +1,7 @@
 use bevy::reflect::Reflect;
+#[cfg(feature = "serialize")]
+use bevy::reflect::{ReflectDeserialize, ReflectSerialize};
+#[cfg(target_os = "horizon")]
 use ctru::services::hid::KeyPad;
 /// A button of a 3ds.
 ///
@@ -28,10 +31,10 @@ impl Button3ds {
     /// # Examples
     ///
     /// ```
-    /// # use bevy_3ds::input::button::{Button3ds, Button3dsType};
+    /// # use bevy_3ds_input::button::{Button3ds, Button3dsType};
     /// #
-    /// let 3ds_button = Button3ds::new(
-    ///     Button3dsType::South,
+    /// let button_3ds = Button3ds::new(
+    ///     Button3dsType::A,
     /// );
     /// ```
     pub fn new(button_type: Button3dsType) -> Self {
@@ -102,6 +105,41 @@ pub enum Button3dsType {
     R,
 }
 
+impl Button3dsType {
+    /// The direction buttons of the circle pad, in the order left, right, up, down that
+    /// [`StickDirections::update_with_events`](crate::stick::StickDirections::update_with_events)
+    /// expects.
+    pub const CPAD_DIRECTIONS: [Button3dsType; 4] = [
+        Button3dsType::CPadLeft,
+        Button3dsType::CPadRight,
+        Button3dsType::CPadUp,
+        Button3dsType::CPadDown,
+    ];
+    /// The direction buttons of the c-stick, in the order left, right, up, down.
+    pub const CSTICK_DIRECTIONS: [Button3dsType; 4] = [
+        Button3dsType::CStickLeft,
+        Button3dsType::CStickRight,
+        Button3dsType::CStickUp,
+        Button3dsType::CStickDown,
+    ];
+
+    /// Whether this is one of the digital directions of the circle pad or c-stick.
+    pub fn is_stick_direction(&self) -> bool {
+        matches!(
+            self,
+            Button3dsType::CPadRight
+                | Button3dsType::CPadLeft
+                | Button3dsType::CPadUp
+                | Button3dsType::CPadDown
+                | Button3dsType::CStickRight
+                | Button3dsType::CStickLeft
+                | Button3dsType::CStickUp
+                | Button3dsType::CStickDown
+        )
+    }
+}
+
+#[cfg(target_os = "horizon")]
 impl TryFrom<KeyPad> for Button3dsType {
     type Error = ();
 
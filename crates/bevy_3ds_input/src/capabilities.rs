@@ -0,0 +1,72 @@
+use bevy::ecs::system::Resource;
+
+/// The controls available on the console the app is running on.
+///
+/// ## Usage
+///
+/// Read this resource to adapt control schemes, e.g. to map camera controls to the
+/// touch screen when there is no c-stick. It is detected once by the
+/// [`InputPlugin`](crate::InputPlugin), inserting it beforehand overrides detection.
+/// Changes reported by the [`InputSource3ds`](crate::source::InputSource3ds) later on,
+/// such as a Circle Pad Pro being used, are applied to it when input is read.
+#[derive(Resource, Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Controller3dsCapabilities {
+    /// Whether the console is a New 3DS (or New 2DS).
+    pub new_3ds: bool,
+    /// Whether there is a c-stick, [`Axis3dsType::CStickX`](crate::axis::Axis3dsType::CStickX)
+    /// and [`Axis3dsType::CStickY`](crate::axis::Axis3dsType::CStickY) stay at zero otherwise.
+    pub c_stick: bool,
+    /// Whether there are ZL and ZR buttons.
+    pub zl_zr: bool,
+    /// Whether a Circle Pad Pro is attached to an Old 3DS, it adds a c-stick and ZL/ZR.
+    ///
+    /// `ir:rst` can't be asked whether one is attached, so it is detected the first time
+    /// its c-stick or ZL/ZR are used, and stays set if it is detached again.
+    pub circle_pad_pro: bool,
+}
+
+impl Controller3dsCapabilities {
+    /// Detects the controls of the console.
+    ///
+    /// The c-stick and ZL/ZR are built into every New 3DS, a Circle Pad Pro is only
+    /// detected once it is used. When not building for the 3ds there is no console to ask,
+    /// so an Old 3DS is reported, the [`InputPlugin`](crate::InputPlugin) uses what its
+    /// [`InputSource3ds`](crate::source::InputSource3ds) reports instead.
+    pub fn detect() -> Self {
+        #[cfg(target_os = "horizon")]
+        {
+            let new_3ds = is_new_3ds();
+            Self {
+                new_3ds,
+                c_stick: new_3ds,
+                zl_zr: new_3ds,
+                circle_pad_pro: false,
+            }
+        }
+        #[cfg(not(target_os = "horizon"))]
+        Self::default()
+    }
+
+    /// The capabilities of a console with a Circle Pad Pro attached.
+    pub fn with_circle_pad_pro(self) -> Self {
+        Self {
+            c_stick: true,
+            zl_zr: true,
+            circle_pad_pro: true,
+            ..self
+        }
+    }
+}
+
+#[cfg(target_os = "horizon")]
+fn is_new_3ds() -> bool {
+    // the check goes through the apt service, which is reference counted so this
+    // doesn't interfere with the runner's handle
+    let Ok(_apt) = ctru::services::apt::Apt::new() else {
+        return false;
+    };
+    let mut new_3ds = false;
+    let result = unsafe { ctru_sys::APT_CheckNew3DS(&mut new_3ds) };
+    // negative results are errors
+    result >= 0 && new_3ds
+}
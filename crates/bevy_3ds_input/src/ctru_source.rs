@@ -0,0 +1,153 @@
+use std::mem::MaybeUninit;
+
+use bevy::math::Vec3;
+use ctru::services::hid::{Hid, KeyPad};
+use tracing::error;
+
+use crate::button::Button3dsType;
+use crate::capabilities::Controller3dsCapabilities;
+use crate::event::Event3ds;
+use crate::hid::{HidEventGenerator3ds, HidFrame3ds};
+use crate::motion::Motion3dsSample;
+use crate::source::InputSource3ds;
+use crate::stick::Input3dsSettings;
+
+// this relies on irrst being initialised, which CtruInputSource3ds keeps open. On an
+// Old 3DS it reads the Circle Pad Pro, and stays at zero without one
+pub fn cstick_position() -> (i16, i16) {
+    let res = unsafe {
+        let mut res = MaybeUninit::uninit();
+        ctru_sys::irrstCstickRead(res.as_mut_ptr());
+        res.assume_init()
+    };
+
+    (res.dx, res.dy)
+}
+
+/// Turns on the accelerometer and gyroscope, they are off by default
+///
+/// Returns whether both sensors were turned on, failures are logged.
+pub fn enable_motion_sensors() -> bool {
+    let accelerometer = unsafe { ctru_sys::HIDUSER_EnableAccelerometer() };
+    let gyroscope = unsafe { ctru_sys::HIDUSER_EnableGyroscope() };
+    // negative results are errors
+    if accelerometer < 0 {
+        error!("failed to enable the accelerometer: {accelerometer:#x}");
+    }
+    if gyroscope < 0 {
+        error!("failed to enable the gyroscope: {gyroscope:#x}");
+    }
+    accelerometer >= 0 && gyroscope >= 0
+}
+
+// like cstick_position this relies on hid being initialised and the sensors
+// having been enabled with enable_motion_sensors
+pub fn motion_sample() -> Motion3dsSample {
+    let (accel, gyro) = unsafe {
+        let mut accel = MaybeUninit::uninit();
+        let mut gyro = MaybeUninit::uninit();
+        ctru_sys::hidAccelRead(accel.as_mut_ptr());
+        ctru_sys::hidGyroRead(gyro.as_mut_ptr());
+        (accel.assume_init(), gyro.assume_init())
+    };
+
+    Motion3dsSample::new(
+        Vec3::new(accel.x as f32, accel.y as f32, accel.z as f32),
+        Vec3::new(gyro.x as f32, gyro.y as f32, gyro.z as f32),
+    )
+}
+
+/// Keeps the ir:rst service, which reads the c-stick and ZL/ZR of a New 3DS or a Circle
+/// Pad Pro, open while alive.
+struct IrRst;
+
+impl IrRst {
+    fn new() -> Option<Self> {
+        // negative results are errors
+        (unsafe { ctru_sys::irrstInit() } >= 0).then_some(IrRst)
+    }
+}
+
+impl Drop for IrRst {
+    fn drop(&mut self) {
+        unsafe { ctru_sys::irrstExit() };
+    }
+}
+
+/// Reads input from the 3ds HID service.
+///
+/// The HID and IR services are kept open for as long as the source is alive. This is
+/// the default [`InputSource3ds`] when building for the 3ds.
+pub struct CtruInputSource3ds {
+    hid: Hid,
+    irrst: Option<IrRst>,
+    capabilities: Controller3dsCapabilities,
+    generator: HidEventGenerator3ds,
+    frame: HidFrame3ds,
+    /// Whether the motion sensors were turned on, `None` until the first poll.
+    motion_enabled: Option<bool>,
+}
+
+impl CtruInputSource3ds {
+    /// Opens the HID and IR services.
+    ///
+    /// The IR service is opened on every console so a Circle Pad Pro can be detected,
+    /// the c-stick is only read when `capabilities` has one.
+    pub fn new(capabilities: &Controller3dsCapabilities) -> ctru::Result<Self> {
+        Ok(Self {
+            hid: Hid::new()?,
+            irrst: IrRst::new(),
+            capabilities: *capabilities,
+            generator: HidEventGenerator3ds::default(),
+            frame: HidFrame3ds::default(),
+            motion_enabled: None,
+        })
+    }
+
+    /// Reads the current state of the controls into `self.frame`.
+    fn read_frame(&mut self) {
+        let hid = &mut self.hid;
+        hid.scan_input();
+        let held = hid.keys_held();
+
+        let frame = &mut self.frame;
+        frame.held.clear();
+        frame.held.extend(
+            held.iter()
+                .filter_map(|key| Button3dsType::try_from(key).ok()),
+        );
+        frame.circle_pad = hid.circlepad_position();
+        let c_stick = self.irrst.as_ref().map(|_| cstick_position());
+        // ir:rst can't say whether a Circle Pad Pro is attached, so it is detected on use
+        if !self.capabilities.c_stick || !self.capabilities.zl_zr {
+            let used = c_stick.is_some_and(|position| position != (0, 0))
+                || frame
+                    .held
+                    .iter()
+                    .any(|button| matches!(button, Button3dsType::ZL | Button3dsType::ZR));
+            if used {
+                self.capabilities = self.capabilities.with_circle_pad_pro();
+            }
+        }
+        frame.c_stick = c_stick.filter(|_| self.capabilities.c_stick);
+        frame.touch = held.contains(KeyPad::TOUCH).then(|| hid.touch_position());
+        frame.volume = hid.volume_slider();
+        frame.slider_3d = ctru::os::current_3d_slider_state();
+        // the generator only sends the sample when it changed
+        frame.motion = (self.motion_enabled == Some(true)).then(motion_sample);
+    }
+}
+
+impl InputSource3ds for CtruInputSource3ds {
+    fn poll(&mut self, settings: &Input3dsSettings, events: &mut Vec<Event3ds>) {
+        if self.motion_enabled.is_none() {
+            self.motion_enabled = Some(enable_motion_sensors());
+        }
+        self.read_frame();
+        self.generator.update(&self.frame, settings, events);
+    }
+
+    fn capabilities(&self) -> Option<Controller3dsCapabilities> {
+        Some(self.capabilities)
+    }
+}
@@ -1,11 +1,15 @@
 use crate::axis::{Axis3ds, Axis3dsType};
 use crate::button::{Button3ds, Button3dsType};
+use crate::motion::Motion3dsSample;
 use bevy::ecs::event::Event;
 use bevy::ecs::event::{EventReader, EventWriter};
 use bevy::ecs::system::ResMut;
+use bevy::input::touch::TouchInput;
 use bevy::input::{Axis, ButtonState, Input};
 use bevy::prelude::DetectChangesMut;
 use bevy::reflect::Reflect;
+#[cfg(feature = "serialize")]
+use bevy::reflect::{ReflectDeserialize, ReflectSerialize};
 /// 3ds event for when the "value" on the axis changes
 #[derive(Event, Debug, Clone, PartialEq, Reflect)]
 #[reflect(Debug, PartialEq)]
@@ -89,6 +93,10 @@ pub enum Event3ds {
     Button(CtruButtonChangedEvent),
     /// An axis of the 3ds has been triggered.
     Axis(Axis3dsChangedEvent),
+    /// The touch panel has been pressed, moved on or released.
+    Touch(TouchInput),
+    /// The motion sensors have been read.
+    Motion(Motion3dsSample),
 }
 
 impl From<CtruButtonChangedEvent> for Event3ds {
@@ -103,11 +111,25 @@ impl From<Axis3dsChangedEvent> for Event3ds {
     }
 }
 
+impl From<TouchInput> for Event3ds {
+    fn from(value: TouchInput) -> Self {
+        Self::Touch(value)
+    }
+}
+
+impl From<Motion3dsSample> for Event3ds {
+    fn from(value: Motion3dsSample) -> Self {
+        Self::Motion(value)
+    }
+}
+
 /// Splits the [`Event3ds`] event stream into it's component events.
 pub fn event_system_3ds(
     mut events_3ds: EventReader<Event3ds>,
     mut button_events: EventWriter<CtruButtonChangedEvent>,
     mut axis_events: EventWriter<Axis3dsChangedEvent>,
+    mut touch_events: EventWriter<TouchInput>,
+    mut motion_samples: EventWriter<Motion3dsSample>,
     mut button_input: ResMut<Input<Button3ds>>,
 ) {
     button_input.bypass_change_detection().clear();
@@ -115,6 +137,8 @@ pub fn event_system_3ds(
         match event_3ds {
             Event3ds::Button(button_event) => button_events.send(*button_event),
             Event3ds::Axis(axis_event) => axis_events.send(axis_event.clone()),
+            Event3ds::Touch(touch_event) => touch_events.send(*touch_event),
+            Event3ds::Motion(sample) => motion_samples.send(*sample),
         }
     }
 }
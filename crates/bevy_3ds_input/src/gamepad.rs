@@ -0,0 +1,298 @@
+use bevy::app::{App, Plugin, PreUpdate};
+use bevy::ecs::event::{EventReader, EventWriter};
+use bevy::ecs::schedule::IntoSystemConfigs;
+use bevy::ecs::system::Local;
+use bevy::input::gamepad::{
+    gamepad_axis_event_system, gamepad_button_event_system, gamepad_connection_system,
+    gamepad_event_system, AxisSettings, ButtonAxisSettings, ButtonSettings, Gamepad, GamepadAxis,
+    GamepadAxisChangedEvent, GamepadAxisType, GamepadButton, GamepadButtonChangedEvent,
+    GamepadButtonInput, GamepadButtonType, GamepadConnection, GamepadConnectionEvent, GamepadEvent,
+    GamepadInfo, GamepadSettings, Gamepads,
+};
+use bevy::input::{Axis, Input, InputSystem};
+
+use crate::axis::Axis3dsType;
+use crate::button::Button3dsType;
+use crate::event::Event3ds;
+use crate::source::Input3dsSourceSet;
+
+/// The [`Gamepad`] the 3ds controls are exposed as.
+pub const GAMEPAD_3DS: Gamepad = Gamepad { id: 0 };
+
+/// The [`GamepadButtonType`] a 3ds button is mirrored to.
+///
+/// | 3ds        | Gamepad                           |
+/// |------------|-----------------------------------|
+/// | A          | [`East`](GamepadButtonType::East)  |
+/// | B          | [`South`](GamepadButtonType::South) |
+/// | X          | [`North`](GamepadButtonType::North) |
+/// | Y          | [`West`](GamepadButtonType::West)  |
+/// | L / R      | `LeftTrigger` / `RightTrigger`    |
+/// | ZL / ZR    | `LeftTrigger2` / `RightTrigger2`  |
+/// | Select     | `Select`                          |
+/// | Start      | `Start`                           |
+/// | DPad       | `DPadUp` / `Down` / `Left` / `Right` |
+///
+/// The face buttons follow their position rather than their label, so `South` is the
+/// bottom button on both a 3ds and an Xbox controller. The circle pad and c-stick
+/// directions aren't mirrored, use the stick axes instead.
+pub fn gamepad_button_type(button: Button3dsType) -> Option<GamepadButtonType> {
+    Some(match button {
+        Button3dsType::A => GamepadButtonType::East,
+        Button3dsType::B => GamepadButtonType::South,
+        Button3dsType::X => GamepadButtonType::North,
+        Button3dsType::Y => GamepadButtonType::West,
+        Button3dsType::L => GamepadButtonType::LeftTrigger,
+        Button3dsType::R => GamepadButtonType::RightTrigger,
+        Button3dsType::ZL => GamepadButtonType::LeftTrigger2,
+        Button3dsType::ZR => GamepadButtonType::RightTrigger2,
+        Button3dsType::Select => GamepadButtonType::Select,
+        Button3dsType::Start => GamepadButtonType::Start,
+        Button3dsType::DPadUp => GamepadButtonType::DPadUp,
+        Button3dsType::DPadDown => GamepadButtonType::DPadDown,
+        Button3dsType::DPadLeft => GamepadButtonType::DPadLeft,
+        Button3dsType::DPadRight => GamepadButtonType::DPadRight,
+        _ => return None,
+    })
+}
+
+/// The [`GamepadAxisType`] a 3ds axis is mirrored to.
+///
+/// The circle pad is the left stick and the c-stick is the right stick. The volume and
+/// 3D sliders aren't mirrored.
+pub fn gamepad_axis_type(axis: Axis3dsType) -> Option<GamepadAxisType> {
+    Some(match axis {
+        Axis3dsType::CPadX => GamepadAxisType::LeftStickX,
+        Axis3dsType::CPadY => GamepadAxisType::LeftStickY,
+        Axis3dsType::CStickX => GamepadAxisType::RightStickX,
+        Axis3dsType::CStickY => GamepadAxisType::RightStickY,
+        _ => return None,
+    })
+}
+
+/// The gamepad buttons the 3ds buttons are mirrored to, see [`gamepad_button_type`].
+const GAMEPAD_3DS_BUTTONS: [GamepadButtonType; 14] = [
+    GamepadButtonType::East,
+    GamepadButtonType::South,
+    GamepadButtonType::North,
+    GamepadButtonType::West,
+    GamepadButtonType::LeftTrigger,
+    GamepadButtonType::RightTrigger,
+    GamepadButtonType::LeftTrigger2,
+    GamepadButtonType::RightTrigger2,
+    GamepadButtonType::Select,
+    GamepadButtonType::Start,
+    GamepadButtonType::DPadUp,
+    GamepadButtonType::DPadDown,
+    GamepadButtonType::DPadLeft,
+    GamepadButtonType::DPadRight,
+];
+
+/// The gamepad axes the 3ds axes are mirrored to, see [`gamepad_axis_type`].
+const GAMEPAD_3DS_AXES: [GamepadAxisType; 4] = [
+    GamepadAxisType::LeftStickX,
+    GamepadAxisType::LeftStickY,
+    GamepadAxisType::RightStickX,
+    GamepadAxisType::RightStickY,
+];
+
+/// Makes the [`GamepadSettings`] of [`GAMEPAD_3DS`] pass values through unchanged.
+///
+/// The sticks already went through the deadzones of the
+/// [`Input3dsSettings`](crate::stick::Input3dsSettings) and buttons are only ever `0.0` or
+/// `1.0`. Settings that were set for [`GAMEPAD_3DS`] before are kept.
+fn pass_through_gamepad_3ds_settings(settings: &mut GamepadSettings) {
+    for button_type in GAMEPAD_3DS_BUTTONS {
+        let button = GamepadButton::new(GAMEPAD_3DS, button_type);
+        settings
+            .button_settings
+            .entry(button)
+            .or_insert_with(|| ButtonSettings::new(1.0, 0.0).unwrap());
+        settings
+            .button_axis_settings
+            .entry(button)
+            .or_insert(ButtonAxisSettings {
+                high: 1.0,
+                low: 0.0,
+                threshold: 0.0,
+            });
+    }
+    for axis_type in GAMEPAD_3DS_AXES {
+        settings
+            .axis_settings
+            .entry(GamepadAxis::new(GAMEPAD_3DS, axis_type))
+            .or_insert_with(|| AxisSettings::new(-1.0, 0.0, 0.0, 1.0, 0.0).unwrap());
+    }
+}
+
+/// Translates the [`Event3ds`] stream into [`GamepadEvent`]s for [`GAMEPAD_3DS`].
+///
+/// The gamepad is connected on the first run.
+pub fn gamepad_3ds_event_system(
+    mut events_3ds: EventReader<Event3ds>,
+    mut gamepad_events: EventWriter<GamepadEvent>,
+    mut connected: Local<bool>,
+) {
+    if !*connected {
+        *connected = true;
+        gamepad_events.send(
+            GamepadConnectionEvent::new(
+                GAMEPAD_3DS,
+                GamepadConnection::Connected(GamepadInfo {
+                    name: "Nintendo 3DS".to_owned(),
+                }),
+            )
+            .into(),
+        );
+    }
+
+    for event in events_3ds.read() {
+        match event {
+            Event3ds::Button(button_event) => {
+                if let Some(button_type) = gamepad_button_type(button_event.button_type) {
+                    let value = if button_event.state.is_pressed() {
+                        1.0
+                    } else {
+                        0.0
+                    };
+                    gamepad_events.send(
+                        GamepadButtonChangedEvent::new(GAMEPAD_3DS, button_type, value).into(),
+                    );
+                }
+            }
+            Event3ds::Axis(axis_event) => {
+                if let Some(axis_type) = gamepad_axis_type(axis_event.axis_type) {
+                    gamepad_events.send(
+                        GamepadAxisChangedEvent::new(GAMEPAD_3DS, axis_type, axis_event.value)
+                            .into(),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Exposes the 3ds controls as a bevy [`Gamepad`], so code written against
+/// [`Input<GamepadButton>`] and [`Axis<GamepadAxis>`] works unchanged.
+///
+/// See [`gamepad_button_type`] and [`gamepad_axis_type`] for the mapping. Requires the
+/// [`InputPlugin`](crate::InputPlugin) and must not be combined with bevy's own `InputPlugin`.
+pub struct Gamepad3dsPlugin;
+
+impl Plugin for Gamepad3dsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<GamepadConnectionEvent>()
+            .add_event::<GamepadButtonChangedEvent>()
+            .add_event::<GamepadButtonInput>()
+            .add_event::<GamepadAxisChangedEvent>()
+            .add_event::<GamepadEvent>()
+            .init_resource::<GamepadSettings>()
+            .init_resource::<Gamepads>()
+            .init_resource::<Input<GamepadButton>>()
+            .init_resource::<Axis<GamepadAxis>>()
+            .init_resource::<Axis<GamepadButton>>()
+            .add_systems(
+                PreUpdate,
+                (
+                    gamepad_3ds_event_system.after(Input3dsSourceSet),
+                    gamepad_event_system.after(gamepad_3ds_event_system),
+                    gamepad_connection_system.after(gamepad_event_system),
+                    gamepad_button_event_system
+                        .after(gamepad_event_system)
+                        .after(gamepad_connection_system),
+                    gamepad_axis_event_system
+                        .after(gamepad_event_system)
+                        .after(gamepad_connection_system),
+                )
+                    .in_set(InputSystem),
+            );
+        pass_through_gamepad_3ds_settings(&mut app.world.resource_mut::<GamepadSettings>());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::{Events, ManualEventReader};
+    use bevy::input::ButtonState;
+
+    use super::*;
+    use crate::event::{Axis3dsChangedEvent, CtruButtonChangedEvent};
+    use crate::InputPlugin;
+
+    fn send(app: &mut App, events: impl IntoIterator<Item = Event3ds>) {
+        app.world.resource_mut::<Events<Event3ds>>().extend(events);
+        app.update();
+    }
+
+    #[test]
+    fn the_3ds_is_a_gamepad() {
+        let mut app = App::new();
+        app.add_plugins((InputPlugin, Gamepad3dsPlugin));
+        let mut connections = ManualEventReader::<GamepadConnectionEvent>::default();
+        let a = GamepadButton::new(GAMEPAD_3DS, GamepadButtonType::East);
+        let left_x = GamepadAxis::new(GAMEPAD_3DS, GamepadAxisType::LeftStickX);
+
+        send(
+            &mut app,
+            [
+                CtruButtonChangedEvent::new(Button3dsType::A, ButtonState::Pressed).into(),
+                // smaller than bevy's default deadzone, the sticks already have their own
+                Axis3dsChangedEvent::new(Axis3dsType::CPadX, 0.03).into(),
+            ],
+        );
+        let events = app.world.resource::<Events<GamepadConnectionEvent>>();
+        let connected: Vec<_> = connections.read(events).cloned().collect();
+        assert_eq!(connected.len(), 1);
+        assert_eq!(connected[0].gamepad, GAMEPAD_3DS);
+        assert!(connected[0].connected());
+        assert!(app.world.resource::<Gamepads>().contains(GAMEPAD_3DS));
+        assert!(app.world.resource::<Input<GamepadButton>>().just_pressed(a));
+        assert_eq!(
+            app.world.resource::<Axis<GamepadAxis>>().get(left_x),
+            Some(0.03)
+        );
+
+        send(
+            &mut app,
+            [
+                CtruButtonChangedEvent::new(Button3dsType::A, ButtonState::Released).into(),
+                Axis3dsChangedEvent::new(Axis3dsType::CPadX, 0.035).into(),
+            ],
+        );
+        let events = app.world.resource::<Events<GamepadConnectionEvent>>();
+        assert_eq!(connections.read(events).count(), 0);
+        assert!(app
+            .world
+            .resource::<Input<GamepadButton>>()
+            .just_released(a));
+        assert_eq!(
+            app.world.resource::<Axis<GamepadAxis>>().get(left_x),
+            Some(0.035)
+        );
+    }
+
+    #[test]
+    fn the_gamepad_settings_pass_values_through() {
+        let mut app = App::new();
+        app.add_plugins((InputPlugin, Gamepad3dsPlugin));
+        let settings = app.world.resource::<GamepadSettings>();
+
+        for axis_type in GAMEPAD_3DS_AXES {
+            let axis = settings.get_axis_settings(GamepadAxis::new(GAMEPAD_3DS, axis_type));
+            for value in [-1.0, -0.99, -0.03, 0.0, 0.01, 0.5, 0.97, 1.0] {
+                assert_eq!(axis.filter(value, None), Some(value));
+            }
+            assert_eq!(axis.filter(0.011, Some(0.01)), Some(0.011));
+        }
+        for button_type in GAMEPAD_3DS_BUTTONS {
+            let button = GamepadButton::new(GAMEPAD_3DS, button_type);
+            let press = settings.get_button_settings(button);
+            assert_eq!(press.press_threshold(), 1.0);
+            assert_eq!(press.release_threshold(), 0.0);
+            let axis = settings.get_button_axis_settings(button);
+            assert_eq!(axis.filter(0.0, Some(1.0)), Some(0.0));
+            assert_eq!(axis.filter(1.0, Some(0.0)), Some(1.0));
+        }
+    }
+}
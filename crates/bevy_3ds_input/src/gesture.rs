@@ -0,0 +1,370 @@
+use std::time::Duration;
+
+use bevy::app::{App, Plugin, PreUpdate};
+use bevy::ecs::event::{Event, EventWriter};
+use bevy::ecs::schedule::IntoSystemConfigs;
+use bevy::ecs::system::{Local, Res, Resource};
+use bevy::input::touch::{touch_screen_input_system, Touches};
+use bevy::input::InputSystem;
+use bevy::math::Vec2;
+use bevy::time::Time;
+
+use crate::touch::TOUCH_ID;
+
+/// The direction of a [`Gesture3ds::Swipe`], in screen space.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SwipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl SwipeDirection {
+    /// The direction `delta` mostly points in, `None` if it is zero.
+    ///
+    /// Positions on the bottom screen grow downwards, so a negative y is [`SwipeDirection::Up`].
+    pub fn from_delta(delta: Vec2) -> Option<Self> {
+        if delta == Vec2::ZERO {
+            return None;
+        }
+        Some(if delta.x.abs() >= delta.y.abs() {
+            if delta.x < 0.0 {
+                Self::Left
+            } else {
+                Self::Right
+            }
+        } else if delta.y < 0.0 {
+            Self::Up
+        } else {
+            Self::Down
+        })
+    }
+}
+
+/// A gesture made on the touch panel.
+///
+/// Positions are in bottom screen pixel space, like [`Touches`].
+#[derive(Event, Debug, Copy, Clone, PartialEq)]
+pub enum Gesture3ds {
+    /// A short touch that didn't move.
+    Tap { position: Vec2 },
+    /// A tap shortly after and close to another tap. The first tap is still sent as a
+    /// [`Gesture3ds::Tap`].
+    DoubleTap { position: Vec2 },
+    /// A touch held in place, sent once while the panel is still touched.
+    LongPress { position: Vec2 },
+    /// The touch moved by `delta` since the last frame. The first drag of a touch is
+    /// measured from where the touch started.
+    Drag { position: Vec2, delta: Vec2 },
+    /// A fast drag, sent when the touch ends. `velocity` is in pixels per second, averaged
+    /// from the start of the drag.
+    Swipe {
+        direction: SwipeDirection,
+        velocity: Vec2,
+    },
+}
+
+/// The thresholds used by the [`GestureRecognizer3ds`].
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct GestureSettings3ds {
+    /// The longest a touch can be held and still be a tap.
+    pub tap_max_duration: Duration,
+    /// The longest time between two taps for them to be a double tap.
+    pub double_tap_interval: Duration,
+    /// The furthest apart two taps can be, in pixels, to be a double tap.
+    pub double_tap_max_distance: f32,
+    /// How long a touch has to be held in place to be a long press.
+    pub long_press_duration: Duration,
+    /// How far, in pixels, a touch has to move before it is a drag.
+    pub drag_threshold: f32,
+    /// The slowest a drag can be, in pixels per second, to be a swipe.
+    pub swipe_min_velocity: f32,
+}
+
+impl Default for GestureSettings3ds {
+    fn default() -> Self {
+        Self {
+            tap_max_duration: Duration::from_millis(250),
+            double_tap_interval: Duration::from_millis(300),
+            double_tap_max_distance: 16.0,
+            long_press_duration: Duration::from_millis(500),
+            drag_threshold: 8.0,
+            swipe_min_velocity: 400.0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct TouchTrack {
+    start_position: Vec2,
+    start_time: Duration,
+    last_position: Vec2,
+    last_time: Duration,
+    /// Where and when the drag started, the last sample before the touch moved past the
+    /// drag threshold.
+    drag_start: Option<(Vec2, Duration)>,
+    long_pressed: bool,
+}
+
+/// Turns timestamped touch samples into [`Gesture3ds`]s.
+///
+/// ## Usage
+///
+/// Call [`GestureRecognizer3ds::update`] once per frame with the current touch position,
+/// which is `None` while the panel isn't touched.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GestureRecognizer3ds {
+    touch: Option<TouchTrack>,
+    last_tap: Option<(Vec2, Duration)>,
+}
+
+impl GestureRecognizer3ds {
+    /// Feeds the sample taken at `now` into the recognizer, pushing any finished
+    /// gestures onto `gestures`.
+    pub fn update(
+        &mut self,
+        sample: Option<Vec2>,
+        now: Duration,
+        settings: &GestureSettings3ds,
+        gestures: &mut Vec<Gesture3ds>,
+    ) {
+        match (self.touch.as_mut(), sample) {
+            (None, None) => {}
+            (None, Some(position)) => {
+                self.touch = Some(TouchTrack {
+                    start_position: position,
+                    start_time: now,
+                    last_position: position,
+                    last_time: now,
+                    drag_start: None,
+                    long_pressed: false,
+                });
+            }
+            (Some(touch), Some(position)) => {
+                if touch.drag_start.is_none()
+                    && touch.start_position.distance(position) > settings.drag_threshold
+                {
+                    touch.drag_start = Some((touch.last_position, touch.last_time));
+                    // the first drag covers the movement inside of the threshold too
+                    touch.last_position = touch.start_position;
+                }
+
+                if touch.drag_start.is_some() {
+                    if position != touch.last_position {
+                        gestures.push(Gesture3ds::Drag {
+                            position,
+                            delta: position - touch.last_position,
+                        });
+                    }
+                } else if !touch.long_pressed
+                    && now.saturating_sub(touch.start_time) >= settings.long_press_duration
+                {
+                    touch.long_pressed = true;
+                    gestures.push(Gesture3ds::LongPress {
+                        position: touch.start_position,
+                    });
+                }
+                touch.last_position = position;
+                touch.last_time = now;
+            }
+            (Some(touch), None) => {
+                let touch = *touch;
+                self.touch = None;
+                self.end_touch(touch, now, settings, gestures);
+            }
+        }
+    }
+
+    fn end_touch(
+        &mut self,
+        touch: TouchTrack,
+        now: Duration,
+        settings: &GestureSettings3ds,
+        gestures: &mut Vec<Gesture3ds>,
+    ) {
+        if let Some((drag_position, drag_time)) = touch.drag_start {
+            // measured over the drag only, holding the touch before dragging doesn't slow it,
+            // and up to the last sample, the release is only noticed a sample later
+            let delta = touch.last_position - drag_position;
+            let duration = touch.last_time.saturating_sub(drag_time);
+            let velocity = delta / duration.as_secs_f32().max(f32::EPSILON);
+            if velocity.length() >= settings.swipe_min_velocity {
+                if let Some(direction) = SwipeDirection::from_delta(delta) {
+                    gestures.push(Gesture3ds::Swipe {
+                        direction,
+                        velocity,
+                    });
+                }
+            }
+            return;
+        }
+
+        let duration = now.saturating_sub(touch.start_time);
+        if touch.long_pressed || duration > settings.tap_max_duration {
+            return;
+        }
+
+        let position = touch.start_position;
+        let double_tap = self.last_tap.is_some_and(|(last_position, last_time)| {
+            now.saturating_sub(last_time) <= settings.double_tap_interval
+                && last_position.distance(position) <= settings.double_tap_max_distance
+        });
+        if double_tap {
+            self.last_tap = None;
+            gestures.push(Gesture3ds::DoubleTap { position });
+        } else {
+            self.last_tap = Some((position, now));
+            gestures.push(Gesture3ds::Tap { position });
+        }
+    }
+}
+
+/// Sends [`Gesture3ds`]s for the touch panel, using the [`GestureSettings3ds`].
+pub fn gesture_3ds_event_system(
+    touches: Res<Touches>,
+    time: Res<Time>,
+    settings: Res<GestureSettings3ds>,
+    mut recognizer: Local<GestureRecognizer3ds>,
+    mut buffer: Local<Vec<Gesture3ds>>,
+    mut gestures: EventWriter<Gesture3ds>,
+) {
+    let sample = touches.get_pressed(TOUCH_ID).map(|touch| touch.position());
+    recognizer.update(sample, time.elapsed(), &settings, &mut buffer);
+    gestures.send_batch(buffer.drain(..));
+}
+
+/// Adds [`Gesture3ds`] events for the touch panel.
+///
+/// Requires the [`InputPlugin`](crate::InputPlugin) and bevy's `TimePlugin`.
+pub struct Gesture3dsPlugin;
+
+impl Plugin for Gesture3dsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Gesture3ds>()
+            .init_resource::<GestureSettings3ds>()
+            .add_systems(
+                PreUpdate,
+                gesture_3ds_event_system
+                    .after(touch_screen_input_system)
+                    .in_set(InputSystem),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds one sample per 10ms, starting at `start` ms, returning the gestures.
+    fn run(
+        recognizer: &mut GestureRecognizer3ds,
+        start: u64,
+        samples: &[Option<Vec2>],
+    ) -> Vec<Gesture3ds> {
+        let settings = GestureSettings3ds::default();
+        let mut gestures = Vec::new();
+        for (i, sample) in samples.iter().enumerate() {
+            let now = Duration::from_millis(start + 10 * i as u64);
+            recognizer.update(*sample, now, &settings, &mut gestures);
+        }
+        gestures
+    }
+
+    #[test]
+    fn taps_and_double_taps() {
+        let mut recognizer = GestureRecognizer3ds::default();
+        let at = Vec2::new(100.0, 100.0);
+        let near = Vec2::new(104.0, 98.0);
+
+        let gestures = run(&mut recognizer, 0, &[Some(at), Some(at), None]);
+        assert_eq!(gestures, vec![Gesture3ds::Tap { position: at }]);
+        let gestures = run(&mut recognizer, 100, &[Some(near), None]);
+        assert_eq!(gestures, vec![Gesture3ds::DoubleTap { position: near }]);
+
+        // too late for a double tap
+        let gestures = run(&mut recognizer, 1000, &[Some(at), None]);
+        let gestures2 = run(&mut recognizer, 1500, &[Some(at), None]);
+        assert_eq!(gestures, vec![Gesture3ds::Tap { position: at }]);
+        assert_eq!(gestures2, gestures);
+    }
+
+    #[test]
+    fn long_press_is_sent_once_and_isnt_a_tap() {
+        let mut recognizer = GestureRecognizer3ds::default();
+        let at = Vec2::new(50.0, 60.0);
+        let mut samples = vec![Some(at); 80];
+        samples.push(None);
+        let gestures = run(&mut recognizer, 0, &samples);
+        assert_eq!(gestures, vec![Gesture3ds::LongPress { position: at }]);
+    }
+
+    #[test]
+    fn first_drag_is_measured_from_the_touch_start() {
+        let mut recognizer = GestureRecognizer3ds::default();
+        let samples = [
+            Some(Vec2::new(10.0, 10.0)),
+            // inside of the drag threshold
+            Some(Vec2::new(15.0, 10.0)),
+            Some(Vec2::new(30.0, 10.0)),
+            Some(Vec2::new(40.0, 10.0)),
+        ];
+        let gestures = run(&mut recognizer, 0, &samples);
+        assert_eq!(
+            gestures,
+            vec![
+                Gesture3ds::Drag {
+                    position: Vec2::new(30.0, 10.0),
+                    delta: Vec2::new(20.0, 0.0),
+                },
+                Gesture3ds::Drag {
+                    position: Vec2::new(40.0, 10.0),
+                    delta: Vec2::new(10.0, 0.0),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn swipe_velocity_is_measured_from_the_drag_start() {
+        let mut recognizer = GestureRecognizer3ds::default();
+        let at = Some(Vec2::new(200.0, 120.0));
+        // held for a second, a long press, then flicked left at 2000 pixels per second
+        let mut samples = vec![at; 100];
+        samples.extend([
+            Some(Vec2::new(180.0, 120.0)),
+            Some(Vec2::new(160.0, 120.0)),
+            Some(Vec2::new(140.0, 120.0)),
+            None,
+        ]);
+        let gestures = run(&mut recognizer, 0, &samples);
+        let Some(Gesture3ds::Swipe {
+            direction,
+            velocity,
+        }) = gestures.last()
+        else {
+            panic!("expected a swipe, got {gestures:?}");
+        };
+        assert_eq!(*direction, SwipeDirection::Left);
+        // 60 pixels over the 30ms from the last held sample to the last moved one
+        assert!((velocity.x + 2000.0).abs() < 1.0, "{velocity}");
+        assert_eq!(velocity.y, 0.0);
+    }
+
+    #[test]
+    fn swipe_directions() {
+        assert_eq!(SwipeDirection::from_delta(Vec2::ZERO), None);
+        assert_eq!(
+            SwipeDirection::from_delta(Vec2::new(3.0, -2.0)),
+            Some(SwipeDirection::Right)
+        );
+        assert_eq!(
+            SwipeDirection::from_delta(Vec2::new(1.0, -2.0)),
+            Some(SwipeDirection::Up)
+        );
+        assert_eq!(
+            SwipeDirection::from_delta(Vec2::new(0.0, 5.0)),
+            Some(SwipeDirection::Down)
+        );
+    }
+}
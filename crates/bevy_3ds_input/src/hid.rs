@@ -0,0 +1,318 @@
+use bevy::input::ButtonState;
+use bevy::math::Vec2;
+use bevy::utils::HashMap;
+
+use crate::axis::Axis3dsType;
+use crate::button::Button3dsType;
+use crate::event::{Axis3dsChangedEvent, CtruButtonChangedEvent, Event3ds};
+use crate::motion::Motion3dsSample;
+use crate::stick::{process_stick, Input3dsSettings, StickDirections};
+use crate::touch::TouchTracker3ds;
+
+/// The raw state of the 3ds controls at one point in time, as read from the HID service.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HidFrame3ds {
+    /// The buttons that are held down. Stick directions are ignored, they are derived
+    /// from the stick positions instead.
+    pub held: Vec<Button3dsType>,
+    /// The raw circle pad position.
+    pub circle_pad: (i16, i16),
+    /// The raw c-stick position, `None` if the console has no c-stick.
+    pub c_stick: Option<(i16, i16)>,
+    /// The raw touch position, `None` while the panel isn't touched.
+    pub touch: Option<(u16, u16)>,
+    /// The volume slider, from `0` to `1`.
+    pub volume: f32,
+    /// The 3D slider, from `0` to `1`.
+    pub slider_3d: f32,
+    /// The motion sensors, `None` if they aren't being read.
+    pub motion: Option<Motion3dsSample>,
+}
+
+/// Turns [`HidFrame3ds`]s into the [`Event3ds`]s for what changed since the last frame.
+///
+/// Every event is a real state change, a button is only pressed or released when it
+/// wasn't already, and an axis is only sent when its value differs from the last one
+/// sent (including going back to zero).
+///
+/// ## Usage
+///
+/// Call [`HidEventGenerator3ds::update`] once per frame with the latest frame.
+#[derive(Debug, Default, Clone)]
+pub struct HidEventGenerator3ds {
+    held: Vec<Button3dsType>,
+    axes: HashMap<Axis3dsType, f32>,
+    cpad_directions: StickDirections,
+    cstick_directions: StickDirections,
+    touch_tracker: TouchTracker3ds,
+    motion: Option<Motion3dsSample>,
+}
+
+impl HidEventGenerator3ds {
+    /// Pushes the events for the changes from the last frame to `frame` onto `events`.
+    pub fn update(
+        &mut self,
+        frame: &HidFrame3ds,
+        settings: &Input3dsSettings,
+        events: &mut Vec<Event3ds>,
+    ) {
+        let held: Vec<_> = frame
+            .held
+            .iter()
+            .copied()
+            .filter(|button_type| !button_type.is_stick_direction())
+            .collect();
+        for button_type in &held {
+            if !self.held.contains(button_type) {
+                events.push(CtruButtonChangedEvent::new(*button_type, ButtonState::Pressed).into());
+            }
+        }
+        for button_type in &self.held {
+            if !held.contains(button_type) {
+                events
+                    .push(CtruButtonChangedEvent::new(*button_type, ButtonState::Released).into());
+            }
+        }
+        self.held = held;
+
+        let cpad = process_stick(
+            Vec2::new(frame.circle_pad.0 as f32, frame.circle_pad.1 as f32),
+            &settings.circle_pad,
+        );
+        self.cpad_directions.update_with_events(
+            cpad,
+            &settings.circle_pad,
+            Button3dsType::CPAD_DIRECTIONS,
+            events,
+        );
+        self.axis(Axis3dsType::CPadX, cpad.x, events);
+        self.axis(Axis3dsType::CPadY, cpad.y, events);
+
+        if let Some((x, y)) = frame.c_stick {
+            let cstick = process_stick(Vec2::new(x as f32, y as f32), &settings.c_stick);
+            self.cstick_directions.update_with_events(
+                cstick,
+                &settings.c_stick,
+                Button3dsType::CSTICK_DIRECTIONS,
+                events,
+            );
+            self.axis(Axis3dsType::CStickX, cstick.x, events);
+            self.axis(Axis3dsType::CStickY, cstick.y, events);
+        }
+
+        if let Some(touch_event) = self.touch_tracker.update(frame.touch) {
+            events.push(touch_event.into());
+        }
+
+        if frame.motion.is_some() && frame.motion != self.motion {
+            self.motion = frame.motion;
+            events.extend(frame.motion.map(Event3ds::from));
+        }
+
+        self.axis(Axis3dsType::Volume, frame.volume, events);
+        self.axis(Axis3dsType::Slider3d, frame.slider_3d, events);
+    }
+
+    /// Sends `value` for `axis_type` if it changed.
+    fn axis(&mut self, axis_type: Axis3dsType, value: f32, events: &mut Vec<Event3ds>) {
+        if self.axes.insert(axis_type, value) != Some(value) {
+            events.push(Axis3dsChangedEvent::new(axis_type, value).into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use bevy::app::App;
+    use bevy::ecs::event::{Events, ManualEventReader};
+    use bevy::input::touch::TouchPhase;
+    use bevy::input::{Axis, Input};
+    use bevy::math::Vec3;
+
+    use super::*;
+    use crate::axis::Axis3ds;
+    use crate::button::Button3ds;
+    use crate::event::Button3dsChangedEvent;
+    use crate::source::{Input3dsSource, InputSource3ds};
+    use crate::InputPlugin;
+
+    fn button(button_type: Button3dsType, state: ButtonState) -> Event3ds {
+        CtruButtonChangedEvent::new(button_type, state).into()
+    }
+
+    fn update(generator: &mut HidEventGenerator3ds, frame: &HidFrame3ds) -> Vec<Event3ds> {
+        let mut events = Vec::new();
+        generator.update(frame, &Input3dsSettings::default(), &mut events);
+        events
+    }
+
+    #[test]
+    fn only_changes_are_sent() {
+        let mut generator = HidEventGenerator3ds::default();
+        let events = update(&mut generator, &HidFrame3ds::default());
+        // every axis is sent once, the c-stick only when there is one
+        assert_eq!(
+            events,
+            vec![
+                Axis3dsChangedEvent::new(Axis3dsType::CPadX, 0.0).into(),
+                Axis3dsChangedEvent::new(Axis3dsType::CPadY, 0.0).into(),
+                Axis3dsChangedEvent::new(Axis3dsType::Volume, 0.0).into(),
+                Axis3dsChangedEvent::new(Axis3dsType::Slider3d, 0.0).into(),
+            ]
+        );
+
+        let frame = HidFrame3ds {
+            // stick directions come from the stick positions instead
+            held: vec![Button3dsType::A, Button3dsType::CPadLeft],
+            volume: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(
+            update(&mut generator, &frame),
+            vec![
+                button(Button3dsType::A, ButtonState::Pressed),
+                Axis3dsChangedEvent::new(Axis3dsType::Volume, 0.5).into(),
+            ]
+        );
+        assert_eq!(update(&mut generator, &frame), vec![]);
+
+        let frame = HidFrame3ds {
+            held: vec![Button3dsType::B],
+            ..frame
+        };
+        assert_eq!(
+            update(&mut generator, &frame),
+            vec![
+                button(Button3dsType::B, ButtonState::Pressed),
+                button(Button3dsType::A, ButtonState::Released),
+            ]
+        );
+    }
+
+    #[test]
+    fn sticks_send_directions_and_axes() {
+        let mut generator = HidEventGenerator3ds::default();
+        update(&mut generator, &HidFrame3ds::default());
+
+        let frame = HidFrame3ds {
+            circle_pad: (0, -150),
+            c_stick: Some((150, 0)),
+            ..Default::default()
+        };
+        assert_eq!(
+            update(&mut generator, &frame),
+            vec![
+                button(Button3dsType::CPadDown, ButtonState::Pressed),
+                Axis3dsChangedEvent::new(Axis3dsType::CPadY, -1.0).into(),
+                button(Button3dsType::CStickRight, ButtonState::Pressed),
+                Axis3dsChangedEvent::new(Axis3dsType::CStickX, 1.0).into(),
+                Axis3dsChangedEvent::new(Axis3dsType::CStickY, 0.0).into(),
+            ]
+        );
+        assert_eq!(update(&mut generator, &frame), vec![]);
+    }
+
+    #[test]
+    fn touch_and_motion_are_deduplicated() {
+        let mut generator = HidEventGenerator3ds::default();
+        update(&mut generator, &HidFrame3ds::default());
+
+        let sample = Motion3dsSample::new(Vec3::new(0.0, -512.0, 0.0), Vec3::ZERO);
+        let frame = HidFrame3ds {
+            touch: Some((20, 30)),
+            motion: Some(sample),
+            ..Default::default()
+        };
+        let events = update(&mut generator, &frame);
+        let [Event3ds::Touch(touch), Event3ds::Motion(motion)] = events.as_slice() else {
+            panic!("expected a touch and a motion sample, got {events:?}");
+        };
+        assert_eq!(touch.phase, TouchPhase::Started);
+        assert_eq!(*motion, sample);
+
+        // a held touch that doesn't move and an unchanged sample send nothing
+        assert_eq!(update(&mut generator, &frame), vec![]);
+
+        // motion that stops being read keeps the last sample
+        let frame = HidFrame3ds {
+            touch: None,
+            motion: None,
+            ..frame
+        };
+        let events = update(&mut generator, &frame);
+        let [Event3ds::Touch(touch)] = events.as_slice() else {
+            panic!("expected the touch to end, got {events:?}");
+        };
+        assert_eq!(touch.phase, TouchPhase::Ended);
+    }
+
+    /// Plays back [`HidFrame3ds`]s through a generator, one per poll.
+    struct FrameSource {
+        frames: VecDeque<HidFrame3ds>,
+        last: HidFrame3ds,
+        generator: HidEventGenerator3ds,
+    }
+
+    impl InputSource3ds for FrameSource {
+        fn poll(&mut self, settings: &Input3dsSettings, events: &mut Vec<Event3ds>) {
+            if let Some(frame) = self.frames.pop_front() {
+                self.last = frame;
+            }
+            self.generator.update(&self.last, settings, events);
+        }
+    }
+
+    #[test]
+    fn held_buttons_are_pressed_once_in_the_app() {
+        let held = HidFrame3ds {
+            held: vec![Button3dsType::X],
+            circle_pad: (150, 0),
+            ..Default::default()
+        };
+        let source = FrameSource {
+            frames: [held.clone(), held.clone(), held, HidFrame3ds::default()].into(),
+            last: HidFrame3ds::default(),
+            generator: HidEventGenerator3ds::default(),
+        };
+        let mut app = App::new();
+        app.insert_resource(Input3dsSource::new(source))
+            .add_plugins(InputPlugin);
+        let mut changes = ManualEventReader::<Button3dsChangedEvent>::default();
+        let mut read_changes = |app: &App| {
+            let events = app.world.resource::<Events<Button3dsChangedEvent>>();
+            changes.read(events).copied().collect::<Vec<_>>()
+        };
+        let x = Button3ds::new(Button3dsType::X);
+        let cpad_x = Axis3ds::new(Axis3dsType::CPadX);
+
+        app.update();
+        assert!(app.world.resource::<Input<Button3ds>>().just_pressed(x));
+        assert_eq!(app.world.resource::<Axis<Axis3ds>>().get(cpad_x), Some(1.0));
+        assert_eq!(
+            read_changes(&app),
+            vec![
+                Button3dsChangedEvent::new(Button3dsType::X, ButtonState::Pressed),
+                Button3dsChangedEvent::new(Button3dsType::CPadRight, ButtonState::Pressed),
+            ]
+        );
+
+        app.update();
+        app.update();
+        let buttons = app.world.resource::<Input<Button3ds>>();
+        assert!(buttons.pressed(x) && !buttons.just_pressed(x));
+        assert_eq!(read_changes(&app), vec![]);
+
+        app.update();
+        assert!(app.world.resource::<Input<Button3ds>>().just_released(x));
+        assert_eq!(app.world.resource::<Axis<Axis3ds>>().get(cpad_x), Some(0.0));
+        assert_eq!(
+            read_changes(&app),
+            vec![
+                Button3dsChangedEvent::new(Button3dsType::X, ButtonState::Released),
+                Button3dsChangedEvent::new(Button3dsType::CPadRight, ButtonState::Released),
+            ]
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use bevy::ecs::event::EventWriter;
+use bevy::ecs::system::{Local, Res, Resource};
+use bevy::input::keyboard::KeyCode;
+use bevy::input::{ButtonState, Input};
+use bevy::math::Vec2;
+use bevy::utils::{HashMap, HashSet};
+
+use crate::axis::Axis3dsType;
+use crate::button::Button3dsType;
+use crate::event::{Axis3dsChangedEvent, CtruButtonChangedEvent, Event3ds};
+use crate::stick::{process_stick, Input3dsSettings, StickDirections, StickSettings};
+
+/// Maps host keyboard keys to 3ds inputs, used when not building for the 3ds.
+///
+/// ## Usage
+///
+/// Modify the tables to change the mapping. The default mapping is:
+///
+/// | Keys               | 3ds                |
+/// |--------------------|--------------------|
+/// | Arrows / WASD      | circle pad         |
+/// | K / J / I / U      | A / B / X / Y      |
+/// | Q / E              | L / R              |
+/// | 1 / 3              | ZL / ZR            |
+/// | T / F / G / H      | DPad up / left / down / right |
+/// | Return / Backspace | Start / Select     |
+#[derive(Resource, Debug, Clone)]
+pub struct KeyboardMapping3ds {
+    /// Keys that press a button.
+    pub buttons: Vec<(KeyCode, Button3dsType)>,
+    /// Keys that push an axis by the given amount while held, multiple held keys add up.
+    ///
+    /// Stick axes are then processed with the [`Input3dsSettings`] of the stick, a
+    /// fully pushed axis standing for the stick at its outer deadzone.
+    pub axes: Vec<(KeyCode, Axis3dsType, f32)>,
+}
+
+impl Default for KeyboardMapping3ds {
+    fn default() -> Self {
+        Self {
+            buttons: vec![
+                (KeyCode::K, Button3dsType::A),
+                (KeyCode::J, Button3dsType::B),
+                (KeyCode::I, Button3dsType::X),
+                (KeyCode::U, Button3dsType::Y),
+                (KeyCode::Q, Button3dsType::L),
+                (KeyCode::E, Button3dsType::R),
+                (KeyCode::Key1, Button3dsType::ZL),
+                (KeyCode::Key3, Button3dsType::ZR),
+                (KeyCode::T, Button3dsType::DPadUp),
+                (KeyCode::F, Button3dsType::DPadLeft),
+                (KeyCode::G, Button3dsType::DPadDown),
+                (KeyCode::H, Button3dsType::DPadRight),
+                (KeyCode::Return, Button3dsType::Start),
+                (KeyCode::Back, Button3dsType::Select),
+            ],
+            axes: vec![
+                (KeyCode::Left, Axis3dsType::CPadX, -1.0),
+                (KeyCode::Right, Axis3dsType::CPadX, 1.0),
+                (KeyCode::Up, Axis3dsType::CPadY, 1.0),
+                (KeyCode::Down, Axis3dsType::CPadY, -1.0),
+                (KeyCode::A, Axis3dsType::CPadX, -1.0),
+                (KeyCode::D, Axis3dsType::CPadX, 1.0),
+                (KeyCode::W, Axis3dsType::CPadY, 1.0),
+                (KeyCode::S, Axis3dsType::CPadY, -1.0),
+            ],
+        }
+    }
+}
+
+/// Turns keyboard state into the [`Event3ds`]s the 3ds would produce.
+#[derive(Debug, Default, Clone)]
+pub struct KeyboardState3ds {
+    buttons: HashSet<Button3dsType>,
+    axes: HashMap<Axis3dsType, f32>,
+    cpad_directions: StickDirections,
+    cstick_directions: StickDirections,
+}
+
+impl KeyboardState3ds {
+    /// Pushes the events for this frame's keyboard state onto `events`.
+    pub fn update(
+        &mut self,
+        keys: &Input<KeyCode>,
+        mapping: &KeyboardMapping3ds,
+        settings: &Input3dsSettings,
+        events: &mut Vec<Event3ds>,
+    ) {
+        // a button is held while any of its keys is, like the axes
+        let buttons: HashSet<Button3dsType> = mapping
+            .buttons
+            .iter()
+            .filter(|(key, _)| keys.pressed(*key))
+            .map(|(_, button_type)| *button_type)
+            .collect();
+        let mut released: Vec<_> = self.buttons.difference(&buttons).copied().collect();
+        released.sort_by_key(|button_type| *button_type as u8);
+        for button_type in released {
+            events.push(CtruButtonChangedEvent::new(button_type, ButtonState::Released).into());
+        }
+        for (_, button_type) in &mapping.buttons {
+            if buttons.contains(button_type) && self.buttons.insert(*button_type) {
+                events.push(CtruButtonChangedEvent::new(*button_type, ButtonState::Pressed).into());
+            }
+        }
+        self.buttons = buttons;
+
+        let mut axes: HashMap<Axis3dsType, f32> = HashMap::default();
+        for (key, axis_type, amount) in &mapping.axes {
+            let value = axes.entry(*axis_type).or_default();
+            if keys.pressed(*key) {
+                *value = (*value + amount).clamp(-1.0, 1.0);
+            }
+        }
+
+        // the sticks go through the same processing as the real ones, with a fully pushed
+        // key at the outer deadzone
+        let mut stick = |x, y, settings: &StickSettings| {
+            let raw = Vec2::new(
+                axes.get(&x).copied().unwrap_or(0.0),
+                axes.get(&y).copied().unwrap_or(0.0),
+            );
+            let value = process_stick(raw * settings.outer_deadzone, settings);
+            for (axis_type, value) in [(x, value.x), (y, value.y)] {
+                if let Some(axis) = axes.get_mut(&axis_type) {
+                    *axis = value;
+                }
+            }
+            value
+        };
+        let cpad = stick(Axis3dsType::CPadX, Axis3dsType::CPadY, &settings.circle_pad);
+        let cstick = stick(
+            Axis3dsType::CStickX,
+            Axis3dsType::CStickY,
+            &settings.c_stick,
+        );
+        self.cpad_directions.update_with_events(
+            cpad,
+            &settings.circle_pad,
+            Button3dsType::CPAD_DIRECTIONS,
+            events,
+        );
+        self.cstick_directions.update_with_events(
+            cstick,
+            &settings.c_stick,
+            Button3dsType::CSTICK_DIRECTIONS,
+            events,
+        );
+
+        for (axis_type, value) in &axes {
+            if self.axes.get(axis_type) != Some(value) {
+                events.push(Axis3dsChangedEvent::new(*axis_type, *value).into());
+            }
+        }
+        self.axes = axes;
+    }
+}
+
+/// Sends [`Event3ds`]s for the host keyboard, using the [`KeyboardMapping3ds`].
+///
+/// Reads [`Input<KeyCode>`], which [`InputPlugin`](crate::InputPlugin) registers along with
+/// bevy's [`keyboard_input_system`](bevy::input::keyboard::keyboard_input_system) when the
+/// app is finished without bevy's own `InputPlugin`. Does nothing until then.
+pub fn keyboard_3ds_event_system(
+    keys: Option<Res<Input<KeyCode>>>,
+    mapping: Res<KeyboardMapping3ds>,
+    settings: Res<Input3dsSettings>,
+    mut state: Local<KeyboardState3ds>,
+    mut buffer: Local<Vec<Event3ds>>,
+    mut events: EventWriter<Event3ds>,
+) {
+    let Some(keys) = keys else {
+        return;
+    };
+    state.update(&keys, &mapping, &settings, &mut buffer);
+    events.send_batch(buffer.drain(..));
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::App;
+    use bevy::ecs::entity::Entity;
+    use bevy::input::keyboard::KeyboardInput;
+    use bevy::input::{Axis, ButtonState};
+
+    use super::*;
+    use crate::axis::Axis3ds;
+    use crate::button::Button3ds;
+    use crate::event::Axis3dsChangedEvent;
+    use crate::InputPlugin;
+
+    #[test]
+    fn sticks_are_processed_like_the_real_ones() {
+        let mut settings = Input3dsSettings::default();
+        settings.circle_pad.response_curve = crate::stick::ResponseCurve::Quadratic;
+        let mut mapping = KeyboardMapping3ds::default();
+        mapping.axes.push((KeyCode::L, Axis3dsType::CPadX, 0.5));
+        let mut keys = Input::<KeyCode>::default();
+        let mut state = KeyboardState3ds::default();
+        let mut events = Vec::new();
+
+        keys.press(KeyCode::Right);
+        state.update(&keys, &mapping, &settings, &mut events);
+        assert!(events.contains(&Axis3dsChangedEvent::new(Axis3dsType::CPadX, 1.0).into()));
+        assert!(events.contains(
+            &CtruButtonChangedEvent::new(Button3dsType::CPadRight, ButtonState::Pressed).into()
+        ));
+
+        // half way at the outer deadzone is through the live range, then squared
+        keys.release(KeyCode::Right);
+        keys.press(KeyCode::L);
+        events.clear();
+        state.update(&keys, &mapping, &settings, &mut events);
+        let [Event3ds::Button(release), Event3ds::Axis(event)] = events.as_slice() else {
+            panic!("expected the direction and axis to change, got {events:?}");
+        };
+        assert_eq!(release.state, ButtonState::Released);
+        let live = (75.0 - 20.0) / (150.0 - 20.0);
+        assert!((event.value - live * live).abs() < 1e-6);
+
+        // unchanged state sends nothing
+        events.clear();
+        state.update(&keys, &mapping, &settings, &mut events);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn buttons_are_held_while_any_of_their_keys_is() {
+        let mut mapping = KeyboardMapping3ds::default();
+        mapping.buttons.push((KeyCode::Space, Button3dsType::A));
+        let settings = Input3dsSettings::default();
+        let mut keys = Input::<KeyCode>::default();
+        let mut state = KeyboardState3ds::default();
+        let mut events = Vec::new();
+        let mut update = |keys: &mut Input<KeyCode>| {
+            events.clear();
+            state.update(keys, &mapping, &settings, &mut events);
+            keys.clear();
+            events.clone()
+        };
+        let pressed: Event3ds =
+            CtruButtonChangedEvent::new(Button3dsType::A, ButtonState::Pressed).into();
+        let released: Event3ds =
+            CtruButtonChangedEvent::new(Button3dsType::A, ButtonState::Released).into();
+        // the axes are sent on the first update
+        update(&mut keys);
+
+        keys.press(KeyCode::K);
+        assert_eq!(update(&mut keys), vec![pressed.clone()]);
+        keys.press(KeyCode::Space);
+        assert!(update(&mut keys).is_empty());
+        keys.release(KeyCode::K);
+        assert!(update(&mut keys).is_empty());
+        keys.release(KeyCode::Space);
+        assert_eq!(update(&mut keys), vec![released.clone()]);
+
+        // both keys going down and up on the same frame change the button once
+        keys.press(KeyCode::K);
+        keys.press(KeyCode::Space);
+        assert_eq!(update(&mut keys), vec![pressed]);
+        keys.release(KeyCode::K);
+        keys.release(KeyCode::Space);
+        assert_eq!(update(&mut keys), vec![released]);
+    }
+
+    #[test]
+    fn keys_are_read_without_bevys_input_plugin() {
+        let mut app = App::new();
+        app.add_plugins(InputPlugin);
+        app.finish();
+        app.cleanup();
+
+        let window = Entity::PLACEHOLDER;
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(KeyCode::K),
+            state: ButtonState::Pressed,
+            window,
+        });
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(KeyCode::D),
+            state: ButtonState::Pressed,
+            window,
+        });
+        app.update();
+
+        let buttons = app.world.resource::<Input<Button3ds>>();
+        assert!(buttons.just_pressed(Button3ds::new(Button3dsType::A)));
+        assert!(buttons.pressed(Button3ds::new(Button3dsType::CPadRight)));
+        let axis = app.world.resource::<Axis<Axis3ds>>();
+        assert_eq!(axis.get(Axis3ds::new(Axis3dsType::CPadX)), Some(1.0));
+    }
+}
@@ -1,26 +1,68 @@
 use axis::{Axis3ds, Axis3dsType};
 use bevy::app::Plugin;
-use bevy::app::PreUpdate;
-use bevy::ecs::event::EventWriter;
+use bevy::app::{First, PreUpdate};
+#[cfg(not(target_os = "horizon"))]
+use bevy::input::keyboard::{keyboard_input_system, KeyCode, KeyboardInput, ScanCode};
+use bevy::input::touch::{touch_screen_input_system, TouchInput, Touches};
 use bevy::input::InputSystem;
-use bevy::input::{Axis, ButtonState, Input};
+use bevy::input::{Axis, Input};
 use bevy::prelude::IntoSystemConfigs;
+use bevy::time::TimeSystem;
 use button::{Button3ds, Button3dsType};
-use ctru::services::hid::Hid;
-use ctru::services::hid::KeyPad;
+use capabilities::Controller3dsCapabilities;
 use event::{
     axis_3ds_event_system, button_3ds_event_system, event_system_3ds, Axis3dsChangedEvent,
     Button3dsChangedEvent, CtruButtonChangedEvent, Event3ds,
 };
-use num_traits::pow::Pow;
-
-use std::mem::MaybeUninit;
+use motion::{motion_3ds_event_system, Motion3ds, Motion3dsEvent, Motion3dsSample};
+use source::{
+    input_source_3ds_capabilities_system, input_source_3ds_event_system,
+    input_source_3ds_time_system, Input3dsSource, Input3dsSourceSet,
+};
+use stick::Input3dsSettings;
+use swkbd::{
+    software_keyboard_system, SoftwareKeyboard3ds, SoftwareKeyboardRequest, TextInputResult,
+};
+#[cfg(target_os = "horizon")]
+use tracing::error;
 
+pub mod action;
 pub mod axis;
 pub mod button;
+pub mod capabilities;
+#[cfg(target_os = "horizon")]
+pub mod ctru_source;
 pub mod event;
+pub mod gamepad;
+pub mod gesture;
+pub mod hid;
+#[cfg(not(target_os = "horizon"))]
+pub mod keyboard;
+pub mod motion;
+#[cfg(feature = "serialize")]
+pub mod record;
+pub mod source;
+pub mod stick;
+pub mod swkbd;
 pub mod test;
-
+pub mod timing;
+pub mod touch;
+
+#[cfg(target_os = "horizon")]
+pub use ctru_source::{cstick_position, enable_motion_sensors, motion_sample};
+
+/// Adds 3ds input support to an app.
+///
+/// Input is read from the [`Input3dsSource`] resource. If one hasn't been inserted,
+/// the HID service is used on the 3ds and an empty
+/// [`MockInputSource3ds`](source::MockInputSource3ds) everywhere else, or if the HID
+/// service can't be opened.
+///
+/// When not building for the 3ds, the host keyboard is also mapped to 3ds inputs
+/// through the `KeyboardMapping3ds` resource. Key state comes from bevy's `InputPlugin`,
+/// or is kept by this plugin when that isn't added.
+///
+/// [`SoftwareKeyboardRequest`]s are shown at the start of the frame, before input is read.
 pub struct InputPlugin;
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
@@ -28,15 +70,29 @@ impl Plugin for InputPlugin {
             .add_event::<CtruButtonChangedEvent>()
             .add_event::<Axis3dsChangedEvent>()
             .add_event::<Event3ds>()
+            .add_event::<TouchInput>()
+            .add_event::<Motion3dsSample>()
+            .add_event::<Motion3dsEvent>()
+            .add_event::<SoftwareKeyboardRequest>()
+            .add_event::<TextInputResult>()
             .init_resource::<Input<Button3ds>>()
             .init_resource::<Axis<Axis3ds>>()
+            .init_resource::<Touches>()
+            .init_resource::<Motion3ds>()
+            .init_resource::<Input3dsSettings>()
+            .init_resource::<SoftwareKeyboard3ds>()
+            .add_systems(First, input_source_3ds_time_system.before(TimeSystem))
             .add_systems(
                 PreUpdate,
                 (
-                    ctru_event_system,
-                    event_system_3ds.after(ctru_event_system),
+                    software_keyboard_system.before(Input3dsSourceSet),
+                    input_source_3ds_event_system.in_set(Input3dsSourceSet),
+                    input_source_3ds_capabilities_system.after(Input3dsSourceSet),
+                    event_system_3ds.after(Input3dsSourceSet),
                     button_3ds_event_system.after(event_system_3ds),
                     axis_3ds_event_system.after(event_system_3ds),
+                    touch_screen_input_system.after(event_system_3ds),
+                    motion_3ds_event_system.after(event_system_3ds),
                 )
                     .in_set(InputSystem),
             );
@@ -44,177 +100,63 @@ impl Plugin for InputPlugin {
         app.register_type::<Button3dsType>()
             .register_type::<Button3ds>()
             .register_type::<Axis3dsType>()
-            .register_type::<Axis3ds>();
-    }
-}
-
-// this relies on irrst already being initialised, which is the case currently
-// but cannot be depended on in general (it will probably crash on Old 3DS)
-pub fn cstick_position() -> (i16, i16) {
-    let res = unsafe {
-        let mut res = MaybeUninit::uninit();
-        ctru_sys::irrstCstickRead(res.as_mut_ptr());
-        res.assume_init()
-    };
-
-    (res.dx, res.dy)
-}
-
-const DEADZONE_BOUND: f32 = 20.0;
-const LIVEZONE_BOUND: f32 = 150.0;
-pub fn ctru_event_system(mut events: EventWriter<Event3ds>) {
-    // TODO: check if it is better to store a handle to the hid as a resource
-    let mut hid = Hid::new().unwrap();
-    hid.scan_input();
-    for key in hid.keys_down() {
-        if let Ok(button_type) = Button3dsType::try_from(key) {
-            events.send(CtruButtonChangedEvent::new(button_type, ButtonState::Pressed).into());
-        }
-    }
-
-    for key in hid.keys_up() {
-        if let Ok(button_type) = Button3dsType::try_from(key) {
-            events.send(CtruButtonChangedEvent::new(button_type, ButtonState::Released).into());
-        }
-    }
-    let (cpad_x, cpad_y) = hid.circlepad_position();
-    let mut cpad_x = cpad_x as f32;
-    let mut cpad_y = cpad_y as f32;
-
-    // calculate the distance from the origin
-    let distance = (cpad_x * cpad_x + cpad_y * cpad_y).sqrt();
-
-    if distance < DEADZONE_BOUND {
-        cpad_x = 0.0;
-        cpad_y = 0.0;
-        events.send(
-            CtruButtonChangedEvent::new(Button3dsType::CPadDown, ButtonState::Released).into(),
-        );
-        events
-            .send(CtruButtonChangedEvent::new(Button3dsType::CPadUp, ButtonState::Released).into());
-        events.send(
-            CtruButtonChangedEvent::new(Button3dsType::CPadLeft, ButtonState::Released).into(),
-        );
-        events.send(
-            CtruButtonChangedEvent::new(Button3dsType::CPadRight, ButtonState::Released).into(),
+            .register_type::<Axis3ds>()
+            .register_type::<Motion3ds>();
+
+        #[cfg(feature = "serialize")]
+        app.add_systems(
+            PreUpdate,
+            record::record_input_3ds_system
+                .after(Input3dsSourceSet)
+                .before(event_system_3ds)
+                .in_set(InputSystem),
         );
-    } else {
-        if cpad_x < 0.0 {
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CPadRight, ButtonState::Released).into(),
-            );
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CPadLeft, ButtonState::Pressed).into(),
-            );
-        } else {
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CPadLeft, ButtonState::Released).into(),
-            );
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CPadRight, ButtonState::Pressed).into(),
-            );
-        }
 
-        if cpad_y < 0.0 {
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CPadUp, ButtonState::Released).into(),
-            );
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CPadDown, ButtonState::Pressed).into(),
-            );
-        } else {
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CPadDown, ButtonState::Released).into(),
-            );
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CPadUp, ButtonState::Pressed).into(),
+        #[cfg(not(target_os = "horizon"))]
+        app.init_resource::<keyboard::KeyboardMapping3ds>()
+            .add_systems(
+                PreUpdate,
+                keyboard::keyboard_3ds_event_system
+                    .after(input_source_3ds_event_system)
+                    .after(keyboard_input_system)
+                    .in_set(Input3dsSourceSet)
+                    .in_set(InputSystem),
             );
-        }
 
-        cpad_x -= cpad_x * DEADZONE_BOUND / distance;
-        cpad_y -= cpad_y * DEADZONE_BOUND / distance;
-    }
-    let adjusted_livezone_bound = LIVEZONE_BOUND - DEADZONE_BOUND; // so that scale is smooth
-    events.send(
-        Axis3dsChangedEvent::new(Axis3dsType::CPadX, cpad_x / adjusted_livezone_bound).into(),
-    );
-    events.send(
-        Axis3dsChangedEvent::new(Axis3dsType::CPadY, cpad_y / adjusted_livezone_bound).into(),
-    );
-
-    let (cstick_x, cstick_y) = cstick_position();
-    let mut cstick_x = cstick_x as f32;
-    let mut cstick_y = cstick_y as f32;
-
-    // calculate the distance from the origin
-    let distance = (cstick_x * cstick_x + cstick_y * cstick_y).sqrt();
-
-    if distance < DEADZONE_BOUND {
-        cstick_x = 0.0;
-        cstick_y = 0.0;
-        events.send(
-            CtruButtonChangedEvent::new(Button3dsType::CStickDown, ButtonState::Released).into(),
-        );
-        events.send(
-            CtruButtonChangedEvent::new(Button3dsType::CStickUp, ButtonState::Released).into(),
-        );
-        events.send(
-            CtruButtonChangedEvent::new(Button3dsType::CStickLeft, ButtonState::Released).into(),
-        );
-        events.send(
-            CtruButtonChangedEvent::new(Button3dsType::CStickRight, ButtonState::Released).into(),
-        );
-    } else {
-        if cstick_x < 0.0 {
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CStickRight, ButtonState::Released)
-                    .into(),
-            );
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CStickLeft, ButtonState::Pressed).into(),
-            );
-        } else {
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CStickLeft, ButtonState::Released)
-                    .into(),
-            );
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CStickRight, ButtonState::Pressed)
-                    .into(),
-            );
+        if !app.world.contains_resource::<Controller3dsCapabilities>() {
+            let reported = app
+                .world
+                .get_resource::<Input3dsSource>()
+                .and_then(|source| source.0.capabilities());
+            app.insert_resource(reported.unwrap_or_else(Controller3dsCapabilities::detect));
         }
 
-        if cstick_y < 0.0 {
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CStickUp, ButtonState::Released).into(),
-            );
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CStickDown, ButtonState::Pressed).into(),
-            );
-        } else {
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CStickDown, ButtonState::Released)
-                    .into(),
-            );
-            events.send(
-                CtruButtonChangedEvent::new(Button3dsType::CStickUp, ButtonState::Pressed).into(),
-            );
+        if !app.world.contains_resource::<Input3dsSource>() {
+            #[cfg(target_os = "horizon")]
+            app.insert_resource(
+                match ctru_source::CtruInputSource3ds::new(
+                    app.world.resource::<Controller3dsCapabilities>(),
+                ) {
+                    Ok(source) => Input3dsSource::new(source),
+                    Err(e) => {
+                        error!("failed to open the HID service, no input will be read: {e}");
+                        Input3dsSource::new(source::MockInputSource3ds::default())
+                    }
+                },
+            );
+            #[cfg(not(target_os = "horizon"))]
+            app.insert_resource(Input3dsSource::new(source::MockInputSource3ds::default()));
         }
-
-        cstick_x -= cstick_x * DEADZONE_BOUND / distance;
-        cstick_y -= cstick_y * DEADZONE_BOUND / distance;
     }
-    events.send(
-        Axis3dsChangedEvent::new(Axis3dsType::CStickX, cstick_x / adjusted_livezone_bound).into(),
-    );
-    events.send(
-        Axis3dsChangedEvent::new(Axis3dsType::CStickY, cstick_y / adjusted_livezone_bound).into(),
-    );
 
-    let volume: f32 = hid.volume_slider();
-    if volume > 0.0 {
-        events.send(Axis3dsChangedEvent::new(Axis3dsType::Volume, volume).into());
+    // the keyboard mapping needs the key state, which bevy's InputPlugin usually keeps
+    #[cfg(not(target_os = "horizon"))]
+    fn finish(&self, app: &mut bevy::prelude::App) {
+        if !app.world.contains_resource::<Input<KeyCode>>() {
+            app.add_event::<KeyboardInput>()
+                .init_resource::<Input<KeyCode>>()
+                .init_resource::<Input<ScanCode>>()
+                .add_systems(PreUpdate, keyboard_input_system.in_set(InputSystem));
+        }
     }
-    // TODO: add cstick (I don't think ctru-rs supports this)
-    // TODO: add 3d slider axis
 }
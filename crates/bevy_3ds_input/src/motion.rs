@@ -0,0 +1,261 @@
+use bevy::ecs::event::{Event, EventReader, EventWriter};
+use bevy::ecs::system::{ResMut, Resource};
+use bevy::math::Vec3;
+use bevy::reflect::Reflect;
+#[cfg(feature = "serialize")]
+use bevy::reflect::{ReflectDeserialize, ReflectSerialize};
+
+/// A single raw reading of the motion sensors, in the units reported by the HID service.
+#[derive(Event, Debug, Clone, Copy, Default, PartialEq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub struct Motion3dsSample {
+    /// The raw accelerometer vector.
+    pub accelerometer: Vec3,
+    /// The raw gyroscope angular rate.
+    pub gyroscope: Vec3,
+}
+
+impl Motion3dsSample {
+    /// Creates a [`Motion3dsSample`].
+    pub fn new(accelerometer: Vec3, gyroscope: Vec3) -> Self {
+        Self {
+            accelerometer,
+            gyroscope,
+        }
+    }
+}
+
+/// A first order low-pass filter used to smooth out sensor noise.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Debug, PartialEq)]
+pub struct LowPassFilter {
+    /// How much of the previous value is kept every sample, in the range `[0, 1)`.
+    ///
+    /// `0.0` disables the filter, values closer to `1.0` smooth more but add latency.
+    pub smoothing: f32,
+}
+
+impl LowPassFilter {
+    /// A filter that passes every sample through unchanged.
+    pub const DISABLED: Self = Self { smoothing: 0.0 };
+
+    /// Creates a [`LowPassFilter`], clamping `smoothing` into its valid range.
+    pub fn new(smoothing: f32) -> Self {
+        Self {
+            smoothing: smoothing.clamp(0.0, 0.999),
+        }
+    }
+
+    /// Blends a new `sample` into the `previous` filtered value.
+    pub fn apply(&self, previous: Vec3, sample: Vec3) -> Vec3 {
+        previous.lerp(sample, 1.0 - self.smoothing)
+    }
+}
+
+impl Default for LowPassFilter {
+    fn default() -> Self {
+        Self::new(0.5)
+    }
+}
+
+/// The state of the accelerometer and gyroscope.
+///
+/// ## Usage
+///
+/// The offsets and filters can be changed at any time, they are applied to the next
+/// sample. [`Motion3ds::calibrate_gyroscope`] can be used while the console is at rest
+/// to remove gyroscope drift.
+///
+/// ## Updating
+///
+/// This resource is updated inside of the [`motion_3ds_event_system`].
+#[derive(Resource, Debug, Clone, PartialEq, Reflect)]
+pub struct Motion3ds {
+    /// The calibrated and filtered accelerometer vector.
+    pub accelerometer: Vec3,
+    /// The calibrated and filtered gyroscope angular rate.
+    pub gyroscope: Vec3,
+    /// Subtracted from every raw accelerometer sample.
+    pub accelerometer_offset: Vec3,
+    /// Subtracted from every raw gyroscope sample.
+    pub gyroscope_offset: Vec3,
+    /// The filter applied to the accelerometer.
+    pub accelerometer_filter: LowPassFilter,
+    /// The filter applied to the gyroscope.
+    pub gyroscope_filter: LowPassFilter,
+    /// The last raw sample, before calibration and filtering.
+    pub raw: Motion3dsSample,
+}
+
+impl Default for Motion3ds {
+    fn default() -> Self {
+        Self {
+            accelerometer: Vec3::ZERO,
+            gyroscope: Vec3::ZERO,
+            accelerometer_offset: Vec3::ZERO,
+            gyroscope_offset: Vec3::ZERO,
+            accelerometer_filter: LowPassFilter::default(),
+            gyroscope_filter: LowPassFilter::default(),
+            raw: Motion3dsSample::default(),
+        }
+    }
+}
+
+impl Motion3ds {
+    /// Calibrates and filters `sample` into the current state.
+    pub fn apply_sample(&mut self, sample: Motion3dsSample) {
+        self.raw = sample;
+        self.accelerometer = self.accelerometer_filter.apply(
+            self.accelerometer,
+            sample.accelerometer - self.accelerometer_offset,
+        );
+        self.gyroscope = self
+            .gyroscope_filter
+            .apply(self.gyroscope, sample.gyroscope - self.gyroscope_offset);
+    }
+
+    /// Uses the average of `resting` samples, taken while the console is not moving,
+    /// as the gyroscope offset.
+    ///
+    /// Does nothing if `resting` is empty.
+    pub fn calibrate_gyroscope(&mut self, resting: &[Motion3dsSample]) {
+        if resting.is_empty() {
+            return;
+        }
+        let sum: Vec3 = resting.iter().map(|s| s.gyroscope).sum();
+        self.gyroscope_offset = sum / resting.len() as f32;
+    }
+}
+
+/// Sent when the filtered value of one of the motion sensors changes.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serialize",
+    derive(serde::Serialize, serde::Deserialize),
+    reflect(Serialize, Deserialize)
+)]
+pub enum Motion3dsEvent {
+    /// The accelerometer vector changed.
+    Accelerometer(Vec3),
+    /// The gyroscope angular rate changed.
+    Gyroscope(Vec3),
+}
+
+/// Uses [`Motion3dsSample`]s to update the [`Motion3ds`] resource.
+pub fn motion_3ds_event_system(
+    mut samples: EventReader<Motion3dsSample>,
+    mut motion: ResMut<Motion3ds>,
+    mut motion_events: EventWriter<Motion3dsEvent>,
+) {
+    for sample in samples.read() {
+        let previous = motion.clone();
+        motion.apply_sample(*sample);
+        if motion.accelerometer != previous.accelerometer {
+            motion_events.send(Motion3dsEvent::Accelerometer(motion.accelerometer));
+        }
+        if motion.gyroscope != previous.gyroscope {
+            motion_events.send(Motion3dsEvent::Gyroscope(motion.gyroscope));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::{App, Update};
+    use bevy::ecs::event::{Events, ManualEventReader};
+
+    use super::*;
+
+    fn sample(accelerometer: Vec3, gyroscope: Vec3) -> Motion3dsSample {
+        Motion3dsSample::new(accelerometer, gyroscope)
+    }
+
+    #[test]
+    fn filter_smoothing_is_clamped() {
+        assert_eq!(LowPassFilter::new(-1.0).smoothing, 0.0);
+        assert_eq!(LowPassFilter::new(2.0).smoothing, 0.999);
+    }
+
+    #[test]
+    fn filter_blends_samples() {
+        let previous = Vec3::ZERO;
+        let sample = Vec3::new(2.0, -4.0, 8.0);
+        assert_eq!(LowPassFilter::DISABLED.apply(previous, sample), sample);
+        assert_eq!(
+            LowPassFilter::new(0.5).apply(previous, sample),
+            Vec3::new(1.0, -2.0, 4.0)
+        );
+        assert_eq!(
+            LowPassFilter::new(0.75).apply(previous, sample),
+            Vec3::new(0.5, -1.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn samples_are_calibrated_then_filtered() {
+        let mut motion = Motion3ds {
+            accelerometer_offset: Vec3::new(1.0, 0.0, 0.0),
+            gyroscope_offset: Vec3::new(0.0, 2.0, 0.0),
+            accelerometer_filter: LowPassFilter::DISABLED,
+            ..Default::default()
+        };
+        let raw = sample(Vec3::new(1.0, 1.0, 1.0), Vec3::new(2.0, 2.0, 2.0));
+        motion.apply_sample(raw);
+        assert_eq!(motion.raw, raw);
+        assert_eq!(motion.accelerometer, Vec3::new(0.0, 1.0, 1.0));
+        // the default filter keeps half of the previous value
+        assert_eq!(motion.gyroscope, Vec3::new(1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn gyroscope_calibration_uses_the_average() {
+        let mut motion = Motion3ds::default();
+        motion.calibrate_gyroscope(&[]);
+        assert_eq!(motion.gyroscope_offset, Vec3::ZERO);
+
+        motion.calibrate_gyroscope(&[
+            sample(Vec3::ZERO, Vec3::new(1.0, 2.0, 3.0)),
+            sample(Vec3::ZERO, Vec3::new(3.0, 4.0, 5.0)),
+        ]);
+        assert_eq!(motion.gyroscope_offset, Vec3::new(2.0, 3.0, 4.0));
+
+        motion.gyroscope_filter = LowPassFilter::DISABLED;
+        motion.apply_sample(sample(Vec3::ZERO, Vec3::new(2.0, 3.0, 4.0)));
+        assert_eq!(motion.gyroscope, Vec3::ZERO);
+    }
+
+    #[test]
+    fn events_are_only_sent_for_changes() {
+        let mut app = App::new();
+        app.add_event::<Motion3dsSample>()
+            .add_event::<Motion3dsEvent>()
+            .insert_resource(Motion3ds {
+                accelerometer_filter: LowPassFilter::DISABLED,
+                gyroscope_filter: LowPassFilter::DISABLED,
+                ..Default::default()
+            })
+            .add_systems(Update, motion_3ds_event_system);
+
+        let mut reader = ManualEventReader::<Motion3dsEvent>::default();
+        let mut send = |sample: Motion3dsSample| {
+            app.world.send_event(sample);
+            app.update();
+            let events = app.world.resource::<Events<Motion3dsEvent>>();
+            reader.read(events).copied().collect::<Vec<_>>()
+        };
+
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        assert_eq!(
+            send(sample(up, Vec3::ZERO)),
+            [Motion3dsEvent::Accelerometer(up)]
+        );
+        assert_eq!(send(sample(up, Vec3::ZERO)), []);
+        assert_eq!(send(sample(up, up)), [Motion3dsEvent::Gyroscope(up)]);
+    }
+}
@@ -0,0 +1,380 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use bevy::app::AppExit;
+use bevy::ecs::event::EventReader;
+use bevy::ecs::system::{Local, Res, ResMut, Resource};
+use bevy::time::{Real, Time};
+use tracing::error;
+
+use crate::event::Event3ds;
+use crate::source::InputSource3ds;
+use crate::stick::Input3dsSettings;
+
+/// Written at the start of every recording, followed by the format version.
+const RECORDING_MAGIC: &[u8; 6] = b"3DSREC";
+const RECORDING_VERSION: u8 = 2;
+
+fn invalid_data(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Writes the recording header, this must be done once before any frames are written.
+pub fn write_header(writer: &mut impl Write) -> io::Result<()> {
+    writer.write_all(RECORDING_MAGIC)?;
+    writer.write_all(&[RECORDING_VERSION])
+}
+
+/// Reads and checks the recording header.
+pub fn read_header(reader: &mut impl Read) -> io::Result<()> {
+    let mut header = [0; RECORDING_MAGIC.len() + 1];
+    reader.read_exact(&mut header)?;
+    if &header[..RECORDING_MAGIC.len()] != RECORDING_MAGIC {
+        return Err(invalid_data("not a 3ds input recording"));
+    }
+    let version = header[RECORDING_MAGIC.len()];
+    if version != RECORDING_VERSION {
+        return Err(invalid_data(format!(
+            "unsupported input recording version {version}"
+        )));
+    }
+    Ok(())
+}
+
+/// A single recorded frame.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RecordedFrame3ds {
+    /// How far [`Time<Real>`] advanced over the frame.
+    pub delta: Duration,
+    /// The frame's events, in the order they happened.
+    pub events: Vec<Event3ds>,
+}
+
+/// Writes a single frame.
+pub fn write_frame(writer: &mut impl Write, frame: &RecordedFrame3ds) -> io::Result<()> {
+    bincode::serialize_into(writer, frame).map_err(invalid_data)
+}
+
+/// Reads a single frame, returning `None` at the end of the recording.
+pub fn read_frame(reader: &mut impl Read) -> io::Result<Option<RecordedFrame3ds>> {
+    match bincode::deserialize_from(reader) {
+        Ok(frame) => Ok(Some(frame)),
+        Err(e) => match *e {
+            bincode::ErrorKind::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+            e => Err(invalid_data(e)),
+        },
+    }
+}
+
+/// Records the [`Event3ds`] stream and the frame time, one entry per frame.
+///
+/// ## Usage
+///
+/// Insert this resource to start recording, remove it to stop. The recording can be
+/// played back with a [`ReplayInputSource3ds`].
+///
+/// The writer is flushed every [`flush_interval`](InputRecorder3ds::with_flush_interval)
+/// frames, when the app exits and when the recorder is dropped, so a crash loses at
+/// most the frames since the last flush.
+///
+/// ## Updating
+///
+/// Frames are written by the [`record_input_3ds_system`].
+#[derive(Resource)]
+pub struct InputRecorder3ds {
+    writer: Option<Box<dyn Write + Send + Sync>>,
+    flush_interval: u32,
+    unflushed: u32,
+}
+
+impl InputRecorder3ds {
+    /// The default number of frames between flushes, about a second at 60 fps.
+    pub const DEFAULT_FLUSH_INTERVAL: u32 = 60;
+
+    /// Creates a recorder that writes to `writer`.
+    pub fn new(writer: impl Write + Send + Sync + 'static) -> io::Result<Self> {
+        let mut writer = Box::new(writer) as Box<dyn Write + Send + Sync>;
+        write_header(&mut writer)?;
+        Ok(Self {
+            writer: Some(writer),
+            flush_interval: Self::DEFAULT_FLUSH_INTERVAL,
+            unflushed: 0,
+        })
+    }
+
+    /// Sets the number of frames written between flushes. Every flush is a write to the
+    /// SD card on the 3ds, so flushing every frame slows the game down.
+    pub fn with_flush_interval(mut self, frames: u32) -> Self {
+        assert!(frames > 0, "the flush interval can't be zero");
+        self.flush_interval = frames;
+        self
+    }
+
+    /// Creates a recorder that writes to the file at `path`, replacing it if it exists.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Whether the recorder is still writing, it stops after an io error.
+    pub fn is_recording(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    /// Writes a frame, flushing if the flush interval has passed.
+    pub fn record_frame(&mut self, frame: &RecordedFrame3ds) -> io::Result<()> {
+        let Some(writer) = &mut self.writer else {
+            return Ok(());
+        };
+        if let Err(e) = write_frame(writer, frame) {
+            self.writer = None;
+            return Err(e);
+        }
+        self.unflushed += 1;
+        if self.unflushed >= self.flush_interval {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the frames written so far.
+    pub fn flush(&mut self) -> io::Result<()> {
+        let Some(writer) = &mut self.writer else {
+            return Ok(());
+        };
+        self.unflushed = 0;
+        writer.flush().inspect_err(|_| self.writer = None)
+    }
+}
+
+impl Drop for InputRecorder3ds {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            error!("failed to flush the input recording: {e}");
+        }
+    }
+}
+
+/// Writes this frame's [`Event3ds`]s and [`Time<Real>`] delta to the
+/// [`InputRecorder3ds`], if there is one.
+pub fn record_input_3ds_system(
+    recorder: Option<ResMut<InputRecorder3ds>>,
+    time: Option<Res<Time<Real>>>,
+    mut events: EventReader<Event3ds>,
+    mut exit: EventReader<AppExit>,
+    mut frame: Local<RecordedFrame3ds>,
+) {
+    let Some(mut recorder) = recorder else {
+        events.clear();
+        exit.clear();
+        return;
+    };
+    frame.delta = time.map_or(Duration::ZERO, |time| time.delta());
+    frame.events.clear();
+    frame.events.extend(events.read().cloned());
+    let mut result = recorder.record_frame(&frame);
+    if exit.read().next().is_some() {
+        result = result.and_then(|_| recorder.flush());
+    }
+    if let Err(e) = result {
+        error!("failed to record input, stopping recording: {e}");
+    }
+}
+
+/// An [`InputSource3ds`] that plays back a recording made with [`InputRecorder3ds`].
+///
+/// Plays back one recorded frame per update, once the recording runs out no more
+/// events are sent. [`Time`] advances by the recorded frame times while the recording
+/// plays, through [`InputSource3ds::frame_delta`], so systems that depend on time see
+/// the same frames as when recording.
+pub struct ReplayInputSource3ds {
+    reader: Box<dyn Read + Send + Sync>,
+    finished: bool,
+    /// The frame read ahead for its delta, played back by the next poll.
+    next: Option<RecordedFrame3ds>,
+}
+
+impl ReplayInputSource3ds {
+    /// Creates a source that plays back the recording read from `reader`.
+    pub fn new(reader: impl Read + Send + Sync + 'static) -> io::Result<Self> {
+        let mut reader = Box::new(reader) as Box<dyn Read + Send + Sync>;
+        read_header(&mut reader)?;
+        Ok(Self {
+            reader,
+            finished: false,
+            next: None,
+        })
+    }
+
+    /// Creates a source that plays back the recording in the file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+
+    /// Whether every recorded frame has been played back.
+    pub fn is_finished(&self) -> bool {
+        self.finished && self.next.is_none()
+    }
+
+    fn read_next(&mut self) -> Option<RecordedFrame3ds> {
+        if self.finished {
+            return None;
+        }
+        match read_frame(&mut self.reader) {
+            Ok(Some(frame)) => return Some(frame),
+            Ok(None) => {}
+            Err(e) => error!("failed to read input recording, stopping replay: {e}"),
+        }
+        self.finished = true;
+        None
+    }
+}
+
+impl InputSource3ds for ReplayInputSource3ds {
+    fn poll(&mut self, _settings: &Input3dsSettings, events: &mut Vec<Event3ds>) {
+        if let Some(frame) = self.next.take().or_else(|| self.read_next()) {
+            events.extend(frame.events);
+        }
+    }
+
+    fn frame_delta(&mut self) -> Option<Duration> {
+        if self.next.is_none() {
+            self.next = self.read_next();
+        }
+        self.next.as_ref().map(|frame| frame.delta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+
+    use bevy::app::App;
+    use bevy::input::{ButtonState, Input};
+    use bevy::time::{TimePlugin, TimeUpdateStrategy};
+
+    use super::*;
+    use crate::button::{Button3ds, Button3dsType};
+    use crate::event::CtruButtonChangedEvent;
+    use crate::source::{Input3dsSource, MockInputSource3ds};
+    use crate::InputPlugin;
+
+    /// A writer whose bytes can be read while it is owned by a recorder.
+    #[derive(Clone, Default)]
+    struct SharedBuffer {
+        bytes: Arc<Mutex<Vec<u8>>>,
+        flushes: Arc<Mutex<usize>>,
+    }
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.bytes.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            *self.flushes.lock().unwrap() += 1;
+            Ok(())
+        }
+    }
+
+    fn press(button_type: Button3dsType) -> Event3ds {
+        CtruButtonChangedEvent::new(button_type, ButtonState::Pressed).into()
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let frames = [
+            RecordedFrame3ds::default(),
+            RecordedFrame3ds {
+                delta: Duration::from_millis(16),
+                events: vec![press(Button3dsType::A), press(Button3dsType::B)],
+            },
+        ];
+        let mut bytes = Vec::new();
+        write_header(&mut bytes).unwrap();
+        for frame in &frames {
+            write_frame(&mut bytes, frame).unwrap();
+        }
+
+        let mut reader = Cursor::new(bytes);
+        read_header(&mut reader).unwrap();
+        for frame in &frames {
+            assert_eq!(read_frame(&mut reader).unwrap().as_ref(), Some(frame));
+        }
+        assert_eq!(read_frame(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn headers_are_checked() {
+        assert!(read_header(&mut Cursor::new(b"NOTREC\x02")).is_err());
+        assert!(read_header(&mut Cursor::new(b"3DSREC\x01")).is_err());
+        assert!(read_header(&mut Cursor::new(b"3DS")).is_err());
+    }
+
+    #[test]
+    fn recorder_flushes_every_interval() {
+        let buffer = SharedBuffer::default();
+        let mut recorder = InputRecorder3ds::new(buffer.clone())
+            .unwrap()
+            .with_flush_interval(3);
+        for _ in 0..7 {
+            recorder.record_frame(&RecordedFrame3ds::default()).unwrap();
+        }
+        assert_eq!(*buffer.flushes.lock().unwrap(), 2);
+        drop(recorder);
+        assert_eq!(*buffer.flushes.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn replay_matches_the_recording() {
+        let deltas = [10, 20, 30, 40].map(Duration::from_millis);
+        let frames = [
+            vec![press(Button3dsType::A)],
+            vec![],
+            vec![
+                CtruButtonChangedEvent::new(Button3dsType::A, ButtonState::Released).into(),
+                press(Button3dsType::X),
+            ],
+            vec![],
+        ];
+
+        let buffer = SharedBuffer::default();
+        let mock = MockInputSource3ds::default();
+        let mut app = App::new();
+        app.add_plugins((TimePlugin, InputPlugin))
+            .insert_resource(Input3dsSource::new(mock.clone()))
+            .insert_resource(InputRecorder3ds::new(buffer.clone()).unwrap());
+        for (delta, events) in deltas.iter().zip(&frames) {
+            app.insert_resource(TimeUpdateStrategy::ManualDuration(*delta));
+            mock.push_frame(events.clone());
+            app.update();
+        }
+        drop(app);
+
+        let bytes = buffer.bytes.lock().unwrap().clone();
+        let mut app = App::new();
+        app.add_plugins((TimePlugin, InputPlugin))
+            .insert_resource(Input3dsSource::new(
+                ReplayInputSource3ds::new(Cursor::new(bytes)).unwrap(),
+            ));
+
+        let a = Button3ds::new(Button3dsType::A);
+        let x = Button3ds::new(Button3dsType::X);
+        let mut states = Vec::new();
+        let mut times = Vec::new();
+        for _ in 0..frames.len() {
+            app.update();
+            let buttons = app.world.resource::<Input<Button3ds>>();
+            states.push((buttons.pressed(a), buttons.pressed(x)));
+            times.push(app.world.resource::<Time<Real>>().delta());
+        }
+        assert_eq!(
+            states,
+            [(true, false), (true, false), (false, true), (false, true)]
+        );
+        // the first update only starts the clock, when recording too
+        assert_eq!(times[1..], deltas[1..]);
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bevy::ecs::event::EventWriter;
+use bevy::ecs::schedule::SystemSet;
+use bevy::ecs::system::{Local, Res, ResMut, Resource};
+use bevy::time::TimeUpdateStrategy;
+
+use crate::capabilities::Controller3dsCapabilities;
+use crate::event::Event3ds;
+use crate::stick::Input3dsSettings;
+
+/// Something that produces the [`Event3ds`] stream, such as the 3ds HID service.
+///
+/// ## Usage
+///
+/// Insert an [`Input3dsSource`] resource wrapping the source to use, this replaces the
+/// default source chosen by [`InputPlugin`](crate::InputPlugin).
+pub trait InputSource3ds: Send + Sync + 'static {
+    /// Called once per frame, pushes this frame's events onto `events` in the order they happened.
+    fn poll(&mut self, settings: &Input3dsSettings, events: &mut Vec<Event3ds>);
+
+    /// How far the coming frame should advance [`Time`](bevy::time::Time), for sources
+    /// that play back a recording. Called at the start of every frame, before
+    /// [`InputSource3ds::poll`].
+    ///
+    /// `None`, the default, leaves time to the clock.
+    fn frame_delta(&mut self) -> Option<Duration> {
+        None
+    }
+
+    /// The controls this source provides, checked every frame after
+    /// [`InputSource3ds::poll`] so that changes end up in [`Controller3dsCapabilities`].
+    ///
+    /// `None`, the default, leaves the capabilities to
+    /// [`Controller3dsCapabilities::detect`].
+    fn capabilities(&self) -> Option<Controller3dsCapabilities> {
+        None
+    }
+}
+
+/// The systems that send the raw [`Event3ds`] stream.
+///
+/// Anything reading [`Event3ds`] directly should run after this set.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Input3dsSourceSet;
+
+/// The [`InputSource3ds`] used by the [`input_source_3ds_event_system`].
+#[derive(Resource)]
+pub struct Input3dsSource(pub Box<dyn InputSource3ds>);
+
+impl Input3dsSource {
+    /// Creates an [`Input3dsSource`].
+    pub fn new(source: impl InputSource3ds) -> Self {
+        Self(Box::new(source))
+    }
+}
+
+/// An [`InputSource3ds`] that plays back scripted frames of events.
+///
+/// Clones share the same script, so a clone can be kept around to push frames after
+/// the source has been inserted into an app.
+///
+/// ## Usage
+///
+/// ```
+/// # use bevy::app::App;
+/// # use bevy::input::{ButtonState, Input};
+/// # use bevy_3ds_input::button::{Button3ds, Button3dsType};
+/// # use bevy_3ds_input::event::CtruButtonChangedEvent;
+/// # use bevy_3ds_input::source::{Input3dsSource, MockInputSource3ds};
+/// # use bevy_3ds_input::InputPlugin;
+/// let mock = MockInputSource3ds::default();
+/// let mut app = App::new();
+/// app.add_plugins(InputPlugin)
+///     .insert_resource(Input3dsSource::new(mock.clone()));
+///
+/// mock.push_frame([CtruButtonChangedEvent::new(Button3dsType::A, ButtonState::Pressed).into()]);
+/// app.update();
+///
+/// let buttons = app.world.resource::<Input<Button3ds>>();
+/// assert!(buttons.just_pressed(Button3ds::new(Button3dsType::A)));
+/// ```
+#[derive(Default, Clone)]
+pub struct MockInputSource3ds {
+    frames: Arc<Mutex<VecDeque<Vec<Event3ds>>>>,
+    capabilities: Arc<Mutex<Controller3dsCapabilities>>,
+}
+
+impl MockInputSource3ds {
+    /// Queues up the events for a frame, frames are played back one per update.
+    pub fn push_frame(&self, events: impl IntoIterator<Item = Event3ds>) {
+        self.frames
+            .lock()
+            .unwrap()
+            .push_back(events.into_iter().collect());
+    }
+
+    /// The number of frames that haven't been played back yet.
+    pub fn pending_frames(&self) -> usize {
+        self.frames.lock().unwrap().len()
+    }
+
+    /// Sets the controls the source reports, those of an Old 3DS by default.
+    pub fn set_capabilities(&self, capabilities: Controller3dsCapabilities) {
+        *self.capabilities.lock().unwrap() = capabilities;
+    }
+}
+
+impl InputSource3ds for MockInputSource3ds {
+    fn poll(&mut self, _settings: &Input3dsSettings, events: &mut Vec<Event3ds>) {
+        if let Some(frame) = self.frames.lock().unwrap().pop_front() {
+            events.extend(frame);
+        }
+    }
+
+    fn capabilities(&self) -> Option<Controller3dsCapabilities> {
+        Some(*self.capabilities.lock().unwrap())
+    }
+}
+
+/// Polls the [`Input3dsSource`] and sends its events as [`Event3ds`].
+pub fn input_source_3ds_event_system(
+    mut source: ResMut<Input3dsSource>,
+    settings: Res<Input3dsSettings>,
+    mut events: EventWriter<Event3ds>,
+    mut buffer: Local<Vec<Event3ds>>,
+) {
+    source.0.poll(&settings, &mut buffer);
+    events.send_batch(buffer.drain(..));
+}
+
+/// Applies changes to the controls reported by the [`Input3dsSource`] to the
+/// [`Controller3dsCapabilities`], see [`InputSource3ds::capabilities`].
+///
+/// What the source reports on the first frame is only remembered, so inserted
+/// capabilities are kept until the source reports something different.
+pub fn input_source_3ds_capabilities_system(
+    source: Res<Input3dsSource>,
+    mut capabilities: ResMut<Controller3dsCapabilities>,
+    mut reported: Local<Option<Controller3dsCapabilities>>,
+) {
+    let current = source.0.capabilities();
+    if current != *reported {
+        if let (Some(current), Some(_)) = (current, *reported) {
+            *capabilities = current;
+        }
+        *reported = current;
+    }
+}
+
+/// Lets the [`Input3dsSource`] drive [`Time`](bevy::time::Time) through the
+/// [`TimeUpdateStrategy`], see [`InputSource3ds::frame_delta`].
+pub fn input_source_3ds_time_system(
+    mut source: ResMut<Input3dsSource>,
+    strategy: Option<ResMut<TimeUpdateStrategy>>,
+    mut driving: Local<bool>,
+) {
+    let Some(mut strategy) = strategy else {
+        return;
+    };
+    match source.0.frame_delta() {
+        Some(delta) => {
+            *strategy = TimeUpdateStrategy::ManualDuration(delta);
+            *driving = true;
+        }
+        // hands time back to the clock once the source stops driving it
+        None if *driving => {
+            *strategy = TimeUpdateStrategy::Automatic;
+            *driving = false;
+        }
+        None => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::App;
+    use bevy::input::{Axis, ButtonState, Input};
+
+    use super::*;
+    use crate::axis::{Axis3ds, Axis3dsType};
+    use crate::button::{Button3ds, Button3dsType};
+    use crate::event::{Axis3dsChangedEvent, CtruButtonChangedEvent};
+    use crate::InputPlugin;
+
+    fn button(button_type: Button3dsType, state: ButtonState) -> Event3ds {
+        CtruButtonChangedEvent::new(button_type, state).into()
+    }
+
+    fn app(source: impl InputSource3ds) -> App {
+        let mut app = App::new();
+        app.insert_resource(Input3dsSource::new(source))
+            .add_plugins(InputPlugin);
+        app
+    }
+
+    #[test]
+    fn mock_frames_play_back_one_per_update() {
+        let mock = MockInputSource3ds::default();
+        let mut app = app(mock.clone());
+
+        mock.push_frame([button(Button3dsType::A, ButtonState::Pressed)]);
+        mock.push_frame([]);
+        mock.push_frame([
+            button(Button3dsType::A, ButtonState::Released),
+            Axis3dsChangedEvent::new(Axis3dsType::CPadX, 0.5).into(),
+        ]);
+        assert_eq!(mock.pending_frames(), 3);
+
+        let a = Button3ds::new(Button3dsType::A);
+        app.update();
+        assert!(app.world.resource::<Input<Button3ds>>().just_pressed(a));
+        assert_eq!(mock.pending_frames(), 2);
+
+        app.update();
+        let buttons = app.world.resource::<Input<Button3ds>>();
+        assert!(buttons.pressed(a) && !buttons.just_pressed(a));
+
+        app.update();
+        assert!(app.world.resource::<Input<Button3ds>>().just_released(a));
+        let axis = app.world.resource::<Axis<Axis3ds>>();
+        assert_eq!(axis.get(Axis3ds::new(Axis3dsType::CPadX)), Some(0.5));
+        assert_eq!(mock.pending_frames(), 0);
+
+        // nothing queued is an empty frame
+        app.update();
+        assert!(!app.world.resource::<Input<Button3ds>>().pressed(a));
+    }
+
+    #[test]
+    fn capabilities_follow_the_source() {
+        let mock = MockInputSource3ds::default();
+        let mut app = app(mock.clone());
+        let capabilities = |app: &App| *app.world.resource::<Controller3dsCapabilities>();
+        assert_eq!(capabilities(&app), Controller3dsCapabilities::default());
+
+        app.update();
+        let attached = Controller3dsCapabilities::default().with_circle_pad_pro();
+        mock.set_capabilities(attached);
+        app.update();
+        assert_eq!(capabilities(&app), attached);
+    }
+
+    #[test]
+    fn inserted_capabilities_are_kept() {
+        let new_3ds = Controller3dsCapabilities {
+            new_3ds: true,
+            c_stick: true,
+            zl_zr: true,
+            circle_pad_pro: false,
+        };
+        let mut app = App::new();
+        app.insert_resource(new_3ds)
+            .insert_resource(Input3dsSource::new(MockInputSource3ds::default()))
+            .add_plugins(InputPlugin);
+        app.update();
+        app.update();
+        assert_eq!(*app.world.resource::<Controller3dsCapabilities>(), new_3ds);
+    }
+
+    #[test]
+    fn inserted_sources_are_kept() {
+        struct HoldB;
+
+        impl InputSource3ds for HoldB {
+            fn poll(&mut self, _settings: &Input3dsSettings, events: &mut Vec<Event3ds>) {
+                events.push(button(Button3dsType::B, ButtonState::Pressed));
+            }
+        }
+
+        let mut app = app(HoldB);
+        app.update();
+        app.update();
+        let buttons = app.world.resource::<Input<Button3ds>>();
+        assert!(buttons.pressed(Button3ds::new(Button3dsType::B)));
+    }
+}
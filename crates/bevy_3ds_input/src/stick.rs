@@ -0,0 +1,305 @@
+use bevy::ecs::system::Resource;
+use bevy::input::ButtonState;
+use bevy::math::Vec2;
+
+use crate::button::Button3dsType;
+use crate::event::{CtruButtonChangedEvent, Event3ds};
+
+/// How the deadzone of a stick is shaped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum DeadzoneMode {
+    /// The deadzone is a circle, the stick is only live once its distance from the
+    /// center leaves it. Preserves the direction of the stick.
+    #[default]
+    Radial,
+    /// Each axis has its own deadzone. Makes it easier to hold exactly one direction.
+    Axial,
+}
+
+/// How the live range of a stick is mapped to the reported value.
+///
+/// The curve is given the distance through the live range, in `[0, 1]`, and should
+/// return a value in the same range.
+#[derive(Debug, Copy, Clone, Default)]
+pub enum ResponseCurve {
+    /// Reports the distance through the live range as is.
+    #[default]
+    Linear,
+    /// Squares the input, giving finer control near the center.
+    Quadratic,
+    /// Calls the function with the input, the result is clamped into `[0, 1]`.
+    Custom(fn(f32) -> f32),
+}
+
+impl ResponseCurve {
+    /// Maps `value`, the distance through the live range in `[0, 1]`, through the curve.
+    pub fn apply(&self, value: f32) -> f32 {
+        match self {
+            ResponseCurve::Linear => value,
+            ResponseCurve::Quadratic => value * value,
+            ResponseCurve::Custom(f) => f(value),
+        }
+    }
+}
+
+/// Settings for processing the raw values of a single stick.
+///
+/// Processed values are clamped to `[-1, 1]`, the raw range of a stick reaches past the
+/// outer deadzone.
+///
+/// The direction buttons of the stick press once an axis reaches
+/// [`direction_press_threshold`](StickSettings::direction_press_threshold), half way
+/// through the live range by default, not as soon as the stick leaves the inner
+/// deadzone. Set it to [`f32::EPSILON`] to press them as soon as the stick is live.
+#[derive(Debug, Clone, Copy)]
+pub struct StickSettings {
+    /// Distance from the center, in raw stick units, below which the stick reads as `0`.
+    pub inner_deadzone: f32,
+    /// Distance from the center, in raw stick units, above which the stick reads as `1`.
+    pub outer_deadzone: f32,
+    /// How the deadzones are applied to the two axes.
+    pub deadzone_mode: DeadzoneMode,
+    /// How the live range between the deadzones is mapped to the processed value.
+    pub response_curve: ResponseCurve,
+    /// Processed value an axis needs to reach for its digital direction to be pressed.
+    pub direction_press_threshold: f32,
+    /// Processed value an axis needs to drop below for its digital direction to be
+    /// released again.
+    ///
+    /// Keeping this below [`StickSettings::direction_press_threshold`] stops the
+    /// direction from flickering when the stick rests near the threshold.
+    pub direction_release_threshold: f32,
+}
+
+impl Default for StickSettings {
+    fn default() -> Self {
+        Self {
+            inner_deadzone: 20.0,
+            outer_deadzone: 150.0,
+            deadzone_mode: DeadzoneMode::default(),
+            response_curve: ResponseCurve::default(),
+            direction_press_threshold: 0.5,
+            direction_release_threshold: 0.4,
+        }
+    }
+}
+
+impl StickSettings {
+    /// Maps a distance from the center onto `[0, 1]` through the deadzones and the
+    /// response curve.
+    fn process_distance(&self, distance: f32) -> f32 {
+        if distance <= self.inner_deadzone {
+            return 0.0;
+        }
+        let live_range = (self.outer_deadzone - self.inner_deadzone).max(f32::EPSILON);
+        let live = ((distance - self.inner_deadzone) / live_range).clamp(0.0, 1.0);
+        self.response_curve.apply(live).clamp(0.0, 1.0)
+    }
+}
+
+/// Settings for the sticks of the 3ds.
+///
+/// ## Usage
+///
+/// Insert or modify this resource to change how [`Axis3dsType::CPadX`](crate::axis::Axis3dsType::CPadX)
+/// and friends, as well as the stick direction buttons, respond to the sticks.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct Input3dsSettings {
+    /// Settings for the circle pad.
+    pub circle_pad: StickSettings,
+    /// Settings for the c-stick.
+    pub c_stick: StickSettings,
+}
+
+/// Turns a raw stick position into a processed one, each axis in `[-1, 1]`.
+pub fn process_stick(raw: Vec2, settings: &StickSettings) -> Vec2 {
+    match settings.deadzone_mode {
+        DeadzoneMode::Radial => {
+            let distance = raw.length();
+            if distance == 0.0 {
+                return Vec2::ZERO;
+            }
+            raw / distance * settings.process_distance(distance)
+        }
+        DeadzoneMode::Axial => Vec2::new(
+            raw.x.signum() * settings.process_distance(raw.x.abs()),
+            raw.y.signum() * settings.process_distance(raw.y.abs()),
+        ),
+    }
+}
+
+/// The digital directions of a stick.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct StickDirections {
+    /// Whether the stick is pushed left.
+    pub left: bool,
+    /// Whether the stick is pushed right.
+    pub right: bool,
+    /// Whether the stick is pushed up.
+    pub up: bool,
+    /// Whether the stick is pushed down.
+    pub down: bool,
+}
+
+impl StickDirections {
+    /// Updates the directions from a processed stick value, applying hysteresis from `settings`.
+    pub fn update(&mut self, value: Vec2, settings: &StickSettings) {
+        let hold = |pressed: bool, amount: f32| {
+            if pressed {
+                amount >= settings.direction_release_threshold
+            } else {
+                amount >= settings.direction_press_threshold
+            }
+        };
+        self.left = hold(self.left, -value.x);
+        self.right = hold(self.right, value.x);
+        self.up = hold(self.up, value.y);
+        self.down = hold(self.down, -value.y);
+    }
+
+    /// The directions in the order left, right, up, down.
+    pub fn as_array(&self) -> [bool; 4] {
+        [self.left, self.right, self.up, self.down]
+    }
+
+    /// Like [`StickDirections::update`], but also pushes an event for every direction that changed.
+    ///
+    /// `buttons` are the direction buttons of the stick in the order left, right, up, down.
+    pub fn update_with_events(
+        &mut self,
+        value: Vec2,
+        settings: &StickSettings,
+        buttons: [Button3dsType; 4],
+        events: &mut Vec<Event3ds>,
+    ) {
+        let previous = *self;
+        self.update(value, settings);
+        for ((was_pressed, pressed), button_type) in previous
+            .as_array()
+            .into_iter()
+            .zip(self.as_array())
+            .zip(buttons)
+        {
+            if was_pressed != pressed {
+                let state = if pressed {
+                    ButtonState::Pressed
+                } else {
+                    ButtonState::Released
+                };
+                events.push(CtruButtonChangedEvent::new(button_type, state).into());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> StickSettings {
+        StickSettings {
+            inner_deadzone: 10.0,
+            outer_deadzone: 110.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn response_curves() {
+        assert_eq!(ResponseCurve::Linear.apply(0.5), 0.5);
+        assert_eq!(ResponseCurve::Quadratic.apply(0.5), 0.25);
+        assert_eq!(ResponseCurve::Custom(|v| 1.0 - v).apply(0.25), 0.75);
+    }
+
+    #[test]
+    fn radial_deadzones() {
+        let settings = settings();
+        assert_eq!(process_stick(Vec2::ZERO, &settings), Vec2::ZERO);
+        assert_eq!(process_stick(Vec2::new(6.0, 8.0), &settings), Vec2::ZERO);
+        // half way through the live range, keeping the direction
+        let half = process_stick(Vec2::new(36.0, 48.0), &settings);
+        assert!((half - Vec2::new(0.3, 0.4)).length() < 1e-6);
+        // past the outer deadzone is clamped
+        assert_eq!(
+            process_stick(Vec2::new(0.0, -170.0), &settings),
+            Vec2::new(0.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn axial_deadzones() {
+        let settings = StickSettings {
+            deadzone_mode: DeadzoneMode::Axial,
+            ..settings()
+        };
+        assert_eq!(
+            process_stick(Vec2::new(60.0, 5.0), &settings),
+            Vec2::new(0.5, 0.0)
+        );
+        assert_eq!(
+            process_stick(Vec2::new(-200.0, -60.0), &settings),
+            Vec2::new(-1.0, -0.5)
+        );
+    }
+
+    #[test]
+    fn curves_apply_to_the_live_range() {
+        let settings = StickSettings {
+            response_curve: ResponseCurve::Quadratic,
+            ..settings()
+        };
+        assert_eq!(
+            process_stick(Vec2::new(60.0, 0.0), &settings),
+            Vec2::new(0.25, 0.0)
+        );
+        let settings = StickSettings {
+            response_curve: ResponseCurve::Custom(|v| v * 4.0),
+            ..settings
+        };
+        assert_eq!(
+            process_stick(Vec2::new(60.0, 0.0), &settings),
+            Vec2::new(1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn directions_have_hysteresis() {
+        let settings = settings();
+        let mut directions = StickDirections::default();
+
+        directions.update(Vec2::new(0.45, -0.6), &settings);
+        assert_eq!(directions.as_array(), [false, false, false, true]);
+        directions.update(Vec2::new(0.5, -0.45), &settings);
+        assert_eq!(directions.as_array(), [false, true, false, true]);
+        // held until the value drops below the release threshold
+        directions.update(Vec2::new(0.41, -0.3), &settings);
+        assert_eq!(directions.as_array(), [false, true, false, false]);
+        directions.update(Vec2::new(-0.39, 0.0), &settings);
+        assert_eq!(directions.as_array(), [false; 4]);
+    }
+
+    #[test]
+    fn direction_events_follow_the_button_order() {
+        let settings = settings();
+        let buttons = [
+            Button3dsType::CPadLeft,
+            Button3dsType::CPadRight,
+            Button3dsType::CPadUp,
+            Button3dsType::CPadDown,
+        ];
+        let mut directions = StickDirections::default();
+        let mut events = Vec::new();
+
+        directions.update_with_events(Vec2::new(-1.0, 1.0), &settings, buttons, &mut events);
+        directions.update_with_events(Vec2::new(-1.0, 1.0), &settings, buttons, &mut events);
+        directions.update_with_events(Vec2::new(0.0, 1.0), &settings, buttons, &mut events);
+        assert_eq!(
+            events,
+            [
+                CtruButtonChangedEvent::new(Button3dsType::CPadLeft, ButtonState::Pressed).into(),
+                CtruButtonChangedEvent::new(Button3dsType::CPadUp, ButtonState::Pressed).into(),
+                CtruButtonChangedEvent::new(Button3dsType::CPadLeft, ButtonState::Released).into(),
+            ] as [Event3ds; 3]
+        );
+    }
+}
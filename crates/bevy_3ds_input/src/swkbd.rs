@@ -0,0 +1,298 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use bevy::ecs::event::{Event, EventReader, EventWriter};
+use bevy::ecs::system::{ResMut, Resource};
+
+/// The layout of the software keyboard.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum SoftwareKeyboardMode {
+    /// The normal keyboard, with the system's predictive text.
+    #[default]
+    Normal,
+    /// A plain QWERTY keyboard.
+    Qwerty,
+    /// A number pad.
+    Numeric,
+}
+
+/// Send this to show the system software keyboard.
+///
+/// The entered text comes back as a [`TextInputResult`]. Requests are shown one at a
+/// time in the order they were sent.
+///
+/// On the 3ds the system applet takes over the screens while it is shown, and blocks the
+/// main thread inside of [`PreUpdate`](bevy::app::PreUpdate) until the user is done, so
+/// nothing else in the app runs in the meantime.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct SoftwareKeyboardRequest {
+    /// Greyed out text shown while nothing has been entered.
+    pub hint_text: String,
+    /// The maximum number of characters that can be entered.
+    pub max_length: u16,
+    pub mode: SoftwareKeyboardMode,
+}
+
+impl SoftwareKeyboardRequest {
+    /// Creates a [`SoftwareKeyboardRequest`].
+    pub fn new(hint_text: impl Into<String>, max_length: u16, mode: SoftwareKeyboardMode) -> Self {
+        Self {
+            hint_text: hint_text.into(),
+            max_length,
+            mode,
+        }
+    }
+}
+
+/// The outcome of a [`SoftwareKeyboardRequest`].
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub enum TextInputResult {
+    /// The user confirmed the text.
+    Entered(String),
+    /// The user backed out of the keyboard.
+    Cancelled,
+}
+
+/// Something that can show a software keyboard.
+pub trait SoftwareKeyboardApplet: Send + Sync + 'static {
+    /// Starts showing the keyboard for `request`.
+    fn open(&mut self, request: &SoftwareKeyboardRequest);
+
+    /// Called every frame while the keyboard is open, returns the result once the user
+    /// is done.
+    fn poll(&mut self) -> Option<TextInputResult>;
+}
+
+/// Whether the software keyboard is showing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SoftwareKeyboardState {
+    /// Nothing is being shown, the next queued request opens on the next update.
+    #[default]
+    Idle,
+    /// The first queued request is being shown.
+    Open,
+}
+
+/// Runs [`SoftwareKeyboardRequest`]s on a [`SoftwareKeyboardApplet`].
+///
+/// ## Updating
+///
+/// This resource is updated inside of the [`software_keyboard_system`].
+#[derive(Resource)]
+pub struct SoftwareKeyboard3ds {
+    applet: Box<dyn SoftwareKeyboardApplet>,
+    queue: VecDeque<SoftwareKeyboardRequest>,
+    state: SoftwareKeyboardState,
+}
+
+impl SoftwareKeyboard3ds {
+    /// Creates a [`SoftwareKeyboard3ds`] that shows requests on `applet`.
+    pub fn new(applet: impl SoftwareKeyboardApplet) -> Self {
+        Self {
+            applet: Box::new(applet),
+            queue: VecDeque::new(),
+            state: SoftwareKeyboardState::Idle,
+        }
+    }
+
+    /// Whether a request is being shown, the keyboard goes back to
+    /// [`SoftwareKeyboardState::Idle`] on the update its result is returned.
+    pub fn state(&self) -> SoftwareKeyboardState {
+        self.state
+    }
+
+    /// The requests that haven't finished yet, including the one being shown.
+    pub fn pending(&self) -> impl Iterator<Item = &SoftwareKeyboardRequest> {
+        self.queue.iter()
+    }
+
+    /// Queues up `requests`, advances the applet and returns the result of the shown
+    /// request if it finished.
+    pub fn update(
+        &mut self,
+        requests: impl IntoIterator<Item = SoftwareKeyboardRequest>,
+    ) -> Option<TextInputResult> {
+        self.queue.extend(requests);
+
+        if self.state == SoftwareKeyboardState::Idle {
+            let request = self.queue.front()?;
+            self.applet.open(request);
+            self.state = SoftwareKeyboardState::Open;
+        }
+
+        let result = self.applet.poll()?;
+        self.queue.pop_front();
+        self.state = SoftwareKeyboardState::Idle;
+        Some(result)
+    }
+}
+
+impl Default for SoftwareKeyboard3ds {
+    fn default() -> Self {
+        #[cfg(target_os = "horizon")]
+        return Self::new(CtruSoftwareKeyboardApplet::default());
+        #[cfg(not(target_os = "horizon"))]
+        return Self::new(StubSoftwareKeyboardApplet::default());
+    }
+}
+
+/// A [`SoftwareKeyboardApplet`] that returns scripted results.
+///
+/// Clones share the same script. While the script is empty the keyboard stays open,
+/// this is the default applet when not building for the 3ds.
+#[derive(Default, Clone)]
+pub struct StubSoftwareKeyboardApplet {
+    results: Arc<Mutex<VecDeque<TextInputResult>>>,
+    opened: Arc<Mutex<Vec<SoftwareKeyboardRequest>>>,
+}
+
+impl StubSoftwareKeyboardApplet {
+    /// Queues up the result for the next request.
+    pub fn push_result(&self, result: TextInputResult) {
+        self.results.lock().unwrap().push_back(result);
+    }
+
+    /// Every request the keyboard has been opened for.
+    pub fn opened(&self) -> Vec<SoftwareKeyboardRequest> {
+        self.opened.lock().unwrap().clone()
+    }
+}
+
+impl SoftwareKeyboardApplet for StubSoftwareKeyboardApplet {
+    fn open(&mut self, request: &SoftwareKeyboardRequest) {
+        self.opened.lock().unwrap().push(request.clone());
+    }
+
+    fn poll(&mut self) -> Option<TextInputResult> {
+        self.results.lock().unwrap().pop_front()
+    }
+}
+
+/// Shows requests on the system software keyboard applet.
+///
+/// The applet takes over the screens and blocks until the user is done, so the result
+/// is always ready on the first poll.
+#[cfg(target_os = "horizon")]
+#[derive(Default)]
+pub struct CtruSoftwareKeyboardApplet {
+    request: Option<SoftwareKeyboardRequest>,
+}
+
+#[cfg(target_os = "horizon")]
+impl SoftwareKeyboardApplet for CtruSoftwareKeyboardApplet {
+    fn open(&mut self, request: &SoftwareKeyboardRequest) {
+        self.request = Some(request.clone());
+    }
+
+    fn poll(&mut self) -> Option<TextInputResult> {
+        let request = self.request.take()?;
+        Some(launch_swkbd(&request))
+    }
+}
+
+#[cfg(target_os = "horizon")]
+fn launch_swkbd(request: &SoftwareKeyboardRequest) -> TextInputResult {
+    use std::ffi::CString;
+    use std::mem::MaybeUninit;
+
+    let kind = match request.mode {
+        SoftwareKeyboardMode::Normal => ctru_sys::SWKBD_TYPE_NORMAL,
+        SoftwareKeyboardMode::Qwerty => ctru_sys::SWKBD_TYPE_QWERTY,
+        SoftwareKeyboardMode::Numeric => ctru_sys::SWKBD_TYPE_NUMPAD,
+    };
+    let hint = CString::new(request.hint_text.replace('\0', "")).unwrap();
+    // the applet writes utf-8, which is up to 4 bytes per character, plus the nul
+    let mut buf = vec![0u8; request.max_length as usize * 4 + 1];
+
+    let button = unsafe {
+        let mut state = MaybeUninit::<ctru_sys::SwkbdState>::zeroed();
+        ctru_sys::swkbdInit(state.as_mut_ptr(), kind, 2, request.max_length as i32);
+        ctru_sys::swkbdSetHintText(state.as_mut_ptr(), hint.as_ptr());
+        ctru_sys::swkbdInputText(state.as_mut_ptr(), buf.as_mut_ptr().cast(), buf.len())
+    };
+
+    if button != ctru_sys::SWKBD_BUTTON_RIGHT {
+        return TextInputResult::Cancelled;
+    }
+    let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    buf.truncate(len);
+    TextInputResult::Entered(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Shows [`SoftwareKeyboardRequest`]s and sends their [`TextInputResult`]s.
+pub fn software_keyboard_system(
+    mut requests: EventReader<SoftwareKeyboardRequest>,
+    mut keyboard: ResMut<SoftwareKeyboard3ds>,
+    mut results: EventWriter<TextInputResult>,
+) {
+    if let Some(result) = keyboard.update(requests.read().cloned()) {
+        results.send(result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::App;
+    use bevy::ecs::event::{Events, ManualEventReader};
+
+    use super::*;
+    use crate::InputPlugin;
+
+    fn request(hint_text: &str) -> SoftwareKeyboardRequest {
+        SoftwareKeyboardRequest::new(hint_text, 8, SoftwareKeyboardMode::Normal)
+    }
+
+    #[test]
+    fn requests_are_shown_one_at_a_time() {
+        let applet = StubSoftwareKeyboardApplet::default();
+        let mut keyboard = SoftwareKeyboard3ds::new(applet.clone());
+        assert_eq!(keyboard.update([]), None);
+        assert_eq!(keyboard.state(), SoftwareKeyboardState::Idle);
+
+        assert_eq!(keyboard.update([request("first"), request("second")]), None);
+        assert_eq!(keyboard.state(), SoftwareKeyboardState::Open);
+        assert_eq!(keyboard.pending().count(), 2);
+
+        // the shown request stays open until the applet has a result
+        assert_eq!(keyboard.update([]), None);
+        assert_eq!(applet.opened(), vec![request("first")]);
+
+        applet.push_result(TextInputResult::Entered("abc".into()));
+        assert_eq!(
+            keyboard.update([]),
+            Some(TextInputResult::Entered("abc".into()))
+        );
+        assert_eq!(keyboard.state(), SoftwareKeyboardState::Idle);
+        assert_eq!(
+            keyboard.pending().collect::<Vec<_>>(),
+            vec![&request("second")]
+        );
+
+        applet.push_result(TextInputResult::Cancelled);
+        assert_eq!(keyboard.update([]), Some(TextInputResult::Cancelled));
+        assert_eq!(applet.opened(), vec![request("first"), request("second")]);
+        assert_eq!(keyboard.pending().count(), 0);
+        assert_eq!(keyboard.state(), SoftwareKeyboardState::Idle);
+    }
+
+    #[test]
+    fn results_are_sent_as_events() {
+        let applet = StubSoftwareKeyboardApplet::default();
+        let mut app = App::new();
+        app.insert_resource(SoftwareKeyboard3ds::new(applet.clone()))
+            .add_plugins(InputPlugin);
+        let mut results = ManualEventReader::<TextInputResult>::default();
+
+        app.world.send_event(request("name"));
+        app.update();
+        assert_eq!(applet.opened(), vec![request("name")]);
+
+        applet.push_result(TextInputResult::Entered("bevy".into()));
+        app.update();
+        let events = app.world.resource::<Events<TextInputResult>>();
+        assert_eq!(
+            results.read(events).cloned().collect::<Vec<_>>(),
+            vec![TextInputResult::Entered("bevy".into())]
+        );
+    }
+}
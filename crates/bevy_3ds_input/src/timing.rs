@@ -0,0 +1,358 @@
+use std::time::Duration;
+
+use bevy::app::{App, Plugin, PreUpdate};
+use bevy::ecs::event::{Event, EventWriter};
+use bevy::ecs::schedule::IntoSystemConfigs;
+use bevy::ecs::system::{Local, Res, Resource};
+use bevy::input::{Input, InputSystem};
+use bevy::time::Time;
+use bevy::utils::HashMap;
+
+use crate::button::{Button3ds, Button3dsType};
+use crate::event::button_3ds_event_system;
+
+/// Sent repeatedly while a button is held, for things like menu navigation.
+///
+/// The first repeat is sent [`ButtonTimingSettings3ds::repeat_delay`] after the press,
+/// the initial press itself isn't repeated.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ButtonRepeat {
+    /// The held button, sent every [`ButtonTimingSettings3ds::repeat_interval`] of app [`Time`].
+    pub button_type: Button3dsType,
+}
+
+/// Sent once when a button has been held for [`ButtonTimingSettings3ds::held_duration`].
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ButtonHeld {
+    /// The button, held for [`ButtonTimingSettings3ds::held_duration`] of app [`Time`].
+    pub button_type: Button3dsType,
+}
+
+/// A combination of buttons pressed together, such as L+R+Select.
+///
+/// Used for [`ChordPressed`] and for [`Binding3ds::Chord`](crate::action::Binding3ds::Chord).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Chord3ds {
+    buttons: Vec<Button3dsType>,
+}
+
+impl Chord3ds {
+    /// Creates a [`Chord3ds`], the order of `buttons` doesn't matter.
+    pub fn new(buttons: impl IntoIterator<Item = Button3dsType>) -> Self {
+        let mut buttons: Vec<_> = buttons.into_iter().collect();
+        buttons.sort_by_key(|button| *button as u8);
+        buttons.dedup();
+        Self { buttons }
+    }
+
+    /// The buttons of the chord, sorted and without duplicates.
+    pub fn buttons(&self) -> &[Button3dsType] {
+        &self.buttons
+    }
+
+    /// Whether every button of the chord is held, never for an empty chord.
+    pub fn pressed(&self, input: &Input<Button3ds>) -> bool {
+        !self.buttons.is_empty()
+            && self
+                .buttons
+                .iter()
+                .all(|button| input.pressed(Button3ds::new(*button)))
+    }
+
+    /// Whether every button of the chord is pressed and the last one went down this frame.
+    pub fn just_pressed(&self, input: &Input<Button3ds>) -> bool {
+        self.pressed(input)
+            && self
+                .buttons
+                .iter()
+                .any(|button| input.just_pressed(Button3ds::new(*button)))
+    }
+}
+
+/// Sent when every button of a registered [`Chord3ds`] is pressed.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct ChordPressed {
+    /// The registered chord whose last button went down this frame, sent once per press.
+    pub chord: Chord3ds,
+}
+
+/// The chords that send [`ChordPressed`].
+#[derive(Resource, Debug, Default, Clone)]
+pub struct ButtonChords3ds {
+    /// The registered chords, without duplicates when added with [`ButtonChords3ds::register`].
+    pub chords: Vec<Chord3ds>,
+}
+
+impl ButtonChords3ds {
+    /// Adds a chord, does nothing if it is already registered.
+    pub fn register(&mut self, chord: Chord3ds) -> &mut Self {
+        if !self.chords.contains(&chord) {
+            self.chords.push(chord);
+        }
+        self
+    }
+}
+
+/// The timings used for [`ButtonRepeat`] and [`ButtonHeld`].
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct ButtonTimingSettings3ds {
+    /// How long a button has to be held before it starts repeating.
+    pub repeat_delay: Duration,
+    /// The time between repeats, zero disables repeating.
+    pub repeat_interval: Duration,
+    /// How long a button has to be held to send a [`ButtonHeld`].
+    pub held_duration: Duration,
+}
+
+impl Default for ButtonTimingSettings3ds {
+    fn default() -> Self {
+        Self {
+            repeat_delay: Duration::from_millis(400),
+            repeat_interval: Duration::from_millis(100),
+            held_duration: Duration::from_secs(1),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct HoldState {
+    held_for: Duration,
+    next_repeat: Duration,
+    held_sent: bool,
+}
+
+/// The events produced by one [`ButtonTimer3ds::update`].
+#[derive(Debug, Default, Clone)]
+pub struct ButtonTimingEvents {
+    /// A repeat for every button that repeated.
+    pub repeats: Vec<ButtonRepeat>,
+    /// The buttons that reached the held duration.
+    pub held: Vec<ButtonHeld>,
+    /// The registered chords that were completed.
+    pub chords: Vec<ChordPressed>,
+}
+
+/// Tracks how long buttons have been held.
+///
+/// ## Usage
+///
+/// Call [`ButtonTimer3ds::update`] once per frame with the time since the last update.
+#[derive(Debug, Default, Clone)]
+pub struct ButtonTimer3ds {
+    held: HashMap<Button3dsType, HoldState>,
+}
+
+impl ButtonTimer3ds {
+    /// Advances the buttons held in `input` by `delta` and pushes the repeats, holds and
+    /// chords that happened onto `events`.
+    ///
+    /// A button repeats at most once per update, so a long frame doesn't send a burst of
+    /// repeats, the missed ones are skipped.
+    pub fn update(
+        &mut self,
+        input: &Input<Button3ds>,
+        delta: Duration,
+        settings: &ButtonTimingSettings3ds,
+        chords: &ButtonChords3ds,
+        events: &mut ButtonTimingEvents,
+    ) {
+        self.held
+            .retain(|button, _| input.pressed(Button3ds::new(*button)));
+
+        for button in input.get_pressed() {
+            let state = match self.held.get_mut(&button.button_type) {
+                Some(state) => {
+                    state.held_for += delta;
+                    state
+                }
+                None => self.held.entry(button.button_type).or_insert(HoldState {
+                    next_repeat: settings.repeat_delay,
+                    ..Default::default()
+                }),
+            };
+
+            if !settings.repeat_interval.is_zero() && state.held_for >= state.next_repeat {
+                while state.next_repeat <= state.held_for {
+                    state.next_repeat += settings.repeat_interval;
+                }
+                events.repeats.push(ButtonRepeat {
+                    button_type: button.button_type,
+                });
+            }
+
+            if !state.held_sent && state.held_for >= settings.held_duration {
+                state.held_sent = true;
+                events.held.push(ButtonHeld {
+                    button_type: button.button_type,
+                });
+            }
+        }
+
+        for chord in &chords.chords {
+            if chord.just_pressed(input) {
+                events.chords.push(ChordPressed {
+                    chord: chord.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// Sends [`ButtonRepeat`], [`ButtonHeld`] and [`ChordPressed`] events, timed with bevy's [`Time`].
+#[allow(clippy::too_many_arguments)]
+pub fn button_timing_3ds_event_system(
+    input: Res<Input<Button3ds>>,
+    time: Res<Time>,
+    settings: Res<ButtonTimingSettings3ds>,
+    chords: Res<ButtonChords3ds>,
+    mut timer: Local<ButtonTimer3ds>,
+    mut buffer: Local<ButtonTimingEvents>,
+    mut repeats: EventWriter<ButtonRepeat>,
+    mut held: EventWriter<ButtonHeld>,
+    mut chord_events: EventWriter<ChordPressed>,
+) {
+    timer.update(&input, time.delta(), &settings, &chords, &mut buffer);
+    repeats.send_batch(buffer.repeats.drain(..));
+    held.send_batch(buffer.held.drain(..));
+    chord_events.send_batch(buffer.chords.drain(..));
+}
+
+/// Adds [`ButtonRepeat`], [`ButtonHeld`] and [`ChordPressed`] events.
+///
+/// Register chords in the [`ButtonChords3ds`] resource. Requires the
+/// [`InputPlugin`](crate::InputPlugin) and bevy's `TimePlugin`.
+pub struct ButtonTiming3dsPlugin;
+
+impl Plugin for ButtonTiming3dsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ButtonRepeat>()
+            .add_event::<ButtonHeld>()
+            .add_event::<ChordPressed>()
+            .init_resource::<ButtonTimingSettings3ds>()
+            .init_resource::<ButtonChords3ds>()
+            .add_systems(
+                PreUpdate,
+                button_timing_3ds_event_system
+                    .after(button_3ds_event_system)
+                    .in_set(InputSystem),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::{Events, ManualEventReader};
+    use bevy::time::{TimePlugin, TimeUpdateStrategy};
+
+    use super::*;
+    use crate::InputPlugin;
+
+    const FRAME: Duration = Duration::from_millis(50);
+
+    /// Runs the timer for `frames` frames of `delta` with `input` unchanged, clearing the
+    /// just pressed state after the first frame.
+    fn run(
+        timer: &mut ButtonTimer3ds,
+        input: &mut Input<Button3ds>,
+        chords: &ButtonChords3ds,
+        frames: usize,
+        delta: Duration,
+    ) -> ButtonTimingEvents {
+        let settings = ButtonTimingSettings3ds::default();
+        let mut events = ButtonTimingEvents::default();
+        for _ in 0..frames {
+            timer.update(input, delta, &settings, chords, &mut events);
+            input.clear();
+        }
+        events
+    }
+
+    #[test]
+    fn repeats_start_after_the_delay() {
+        let mut timer = ButtonTimer3ds::default();
+        let mut input = Input::default();
+        let chords = ButtonChords3ds::default();
+        input.press(Button3ds::new(Button3dsType::DPadDown));
+
+        // the press frame and 350ms after it
+        let events = run(&mut timer, &mut input, &chords, 8, FRAME);
+        assert!(events.repeats.is_empty());
+        // 400ms, then every 100ms
+        let events = run(&mut timer, &mut input, &chords, 5, FRAME);
+        assert_eq!(events.repeats.len(), 3);
+        assert!(events.held.is_empty());
+
+        let events = run(&mut timer, &mut input, &chords, 8, FRAME);
+        assert_eq!(
+            events.held,
+            vec![ButtonHeld {
+                button_type: Button3dsType::DPadDown
+            }]
+        );
+
+        // releasing starts over
+        input.release(Button3ds::new(Button3dsType::DPadDown));
+        run(&mut timer, &mut input, &chords, 1, FRAME);
+        input.press(Button3ds::new(Button3dsType::DPadDown));
+        let events = run(&mut timer, &mut input, &chords, 8, FRAME);
+        assert!(events.repeats.is_empty());
+    }
+
+    #[test]
+    fn long_frames_repeat_once() {
+        let mut timer = ButtonTimer3ds::default();
+        let mut input = Input::default();
+        let chords = ButtonChords3ds::default();
+        input.press(Button3ds::new(Button3dsType::A));
+        run(&mut timer, &mut input, &chords, 1, FRAME);
+
+        // a second long hitch passes the delay and several intervals
+        let events = run(&mut timer, &mut input, &chords, 1, Duration::from_secs(1));
+        assert_eq!(events.repeats.len(), 1);
+        // and the next repeat is an interval after it, not one of the skipped ones
+        let events = run(&mut timer, &mut input, &chords, 1, FRAME);
+        assert!(events.repeats.is_empty());
+        let events = run(&mut timer, &mut input, &chords, 1, FRAME);
+        assert_eq!(events.repeats.len(), 1);
+    }
+
+    #[test]
+    fn chords_complete_once() {
+        let chord = Chord3ds::new([Button3dsType::R, Button3dsType::L, Button3dsType::L]);
+        assert_eq!(chord.buttons(), &[Button3dsType::L, Button3dsType::R]);
+        let mut chords = ButtonChords3ds::default();
+        chords.register(chord.clone()).register(chord.clone());
+        assert_eq!(chords.chords.len(), 1);
+
+        let mut timer = ButtonTimer3ds::default();
+        let mut input = Input::default();
+        input.press(Button3ds::new(Button3dsType::L));
+        let events = run(&mut timer, &mut input, &chords, 2, FRAME);
+        assert!(events.chords.is_empty());
+
+        input.press(Button3ds::new(Button3dsType::R));
+        let events = run(&mut timer, &mut input, &chords, 3, FRAME);
+        assert_eq!(events.chords, vec![ChordPressed { chord }]);
+    }
+
+    #[test]
+    fn events_follow_the_app_clock() {
+        let mut app = App::new();
+        app.add_plugins((TimePlugin, InputPlugin, ButtonTiming3dsPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME));
+        let mut repeats = ManualEventReader::<ButtonRepeat>::default();
+
+        app.world
+            .resource_mut::<Input<Button3ds>>()
+            .press(Button3ds::new(Button3dsType::Start));
+        let mut count = 0;
+        for _ in 0..13 {
+            app.update();
+            let events = app.world.resource::<Events<ButtonRepeat>>();
+            count += repeats.read(events).count();
+        }
+        // the first update has no delta, 600ms held
+        assert_eq!(count, 3);
+    }
+}
@@ -0,0 +1,109 @@
+use bevy::input::touch::{TouchInput, TouchPhase};
+use bevy::math::Vec2;
+
+/// Width of the bottom (touch) screen in pixels.
+pub const BOTTOM_SCREEN_WIDTH: f32 = 320.0;
+/// Height of the bottom (touch) screen in pixels.
+pub const BOTTOM_SCREEN_HEIGHT: f32 = 240.0;
+
+/// The id given to the touch panel "finger".
+///
+/// The 3ds touch panel is resistive and can only report a single point, so every
+/// [`TouchInput`] produced by this crate uses the same id.
+pub const TOUCH_ID: u64 = 0;
+
+/// Converts a raw HID touch sample into a position in bottom screen pixel space.
+///
+/// The origin is the top left corner of the bottom screen, matching the
+/// coordinate space bevy uses for touches on a window.
+pub fn touch_sample_to_position(x: u16, y: u16) -> Vec2 {
+    Vec2::new(
+        (x as f32).min(BOTTOM_SCREEN_WIDTH - 1.0),
+        (y as f32).min(BOTTOM_SCREEN_HEIGHT - 1.0),
+    )
+}
+
+/// Tracks the touch panel across frames and turns raw samples into [`TouchInput`] events.
+///
+/// ## Usage
+///
+/// Call [`TouchTracker3ds::update`] once per frame with the current sample, which is
+/// `None` while the panel isn't touched.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TouchTracker3ds {
+    last_position: Option<Vec2>,
+}
+
+impl TouchTracker3ds {
+    /// The position of the current touch, if the panel is touched.
+    pub fn position(&self) -> Option<Vec2> {
+        self.last_position
+    }
+
+    /// Feeds a new sample into the tracker, returning the event for the transition if
+    /// there was one.
+    ///
+    /// - nothing to touched is [`TouchPhase::Started`]
+    /// - touched to touched at a different position is [`TouchPhase::Moved`]
+    /// - touched to nothing is [`TouchPhase::Ended`] at the last known position
+    pub fn update(&mut self, sample: Option<(u16, u16)>) -> Option<TouchInput> {
+        let position = sample.map(|(x, y)| touch_sample_to_position(x, y));
+        let phase = match (self.last_position, position) {
+            (None, None) => return None,
+            (None, Some(_)) => TouchPhase::Started,
+            (Some(last), Some(pos)) if last == pos => return None,
+            (Some(_), Some(_)) => TouchPhase::Moved,
+            (Some(_), None) => TouchPhase::Ended,
+        };
+        let event_position = position.or(self.last_position).unwrap();
+        self.last_position = position;
+
+        Some(TouchInput {
+            phase,
+            position: event_position,
+            force: None,
+            id: TOUCH_ID,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_are_clamped_to_the_screen() {
+        assert_eq!(touch_sample_to_position(0, 0), Vec2::ZERO);
+        assert_eq!(touch_sample_to_position(160, 120), Vec2::new(160.0, 120.0));
+        assert_eq!(touch_sample_to_position(319, 239), Vec2::new(319.0, 239.0));
+        assert_eq!(
+            touch_sample_to_position(4000, 4000),
+            Vec2::new(BOTTOM_SCREEN_WIDTH - 1.0, BOTTOM_SCREEN_HEIGHT - 1.0)
+        );
+    }
+
+    #[test]
+    fn tracker_reports_each_transition() {
+        let mut tracker = TouchTracker3ds::default();
+        assert_eq!(tracker.update(None), None);
+
+        let started = tracker.update(Some((10, 20))).unwrap();
+        assert_eq!(started.phase, TouchPhase::Started);
+        assert_eq!(started.position, Vec2::new(10.0, 20.0));
+        assert_eq!(started.id, TOUCH_ID);
+        assert_eq!(tracker.position(), Some(Vec2::new(10.0, 20.0)));
+
+        // holding still isn't a move
+        assert_eq!(tracker.update(Some((10, 20))), None);
+
+        let moved = tracker.update(Some((30, 40))).unwrap();
+        assert_eq!(moved.phase, TouchPhase::Moved);
+        assert_eq!(moved.position, Vec2::new(30.0, 40.0));
+
+        let ended = tracker.update(None).unwrap();
+        assert_eq!(ended.phase, TouchPhase::Ended);
+        assert_eq!(ended.position, Vec2::new(30.0, 40.0));
+        assert_eq!(tracker.position(), None);
+        assert_eq!(tracker.update(None), None);
+    }
+}
@@ -36,6 +36,7 @@ pub mod material;
 pub mod materials;
 pub mod mesh;
 pub mod pass;
+pub mod pipelined;
 pub mod pipeline;
 pub mod plugin;
 mod prep_asset;
@@ -43,8 +44,10 @@ pub mod shader;
 pub mod texture;
 pub mod vertattr;
 
+pub use bevy_3ds_core::pacing;
 pub use citro3d;
 
+/// The screens, only usable from the thread that runs the render world.
 pub struct GfxInstance(pub ctru::services::gfx::Gfx);
 
 pub type StereoFunction = fn(&ExtractedView) -> Option<(ExtractedView, ExtractedView)>;
@@ -162,6 +165,10 @@ impl Default for GfxInstance {
     }
 }
 
+/// The gpu, lives in the render world.
+///
+/// It is only used by the thread running the render world (and the render schedule's
+/// systems), the lock keeps those systems from recording commands at the same time.
 #[derive(Resource)]
 pub struct GpuDevice {
     instance: Mutex<citro3d::Instance>,
@@ -0,0 +1,293 @@
+use std::ffi::c_void;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+
+use bevy::app::{App, AppLabel, Main, Plugin, SubApp};
+use bevy::ecs::system::Resource;
+use bevy::ecs::world::World;
+use bevy::render::RenderApp;
+
+use crate::plugin::init_gpu;
+
+/// The sub app that lends the main world to the render thread, it replaces [`RenderApp`]
+/// when [`PipelinedRendering3dsPlugin`] is used.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, AppLabel)]
+pub struct RenderExtractApp;
+
+/// Starts the render thread with the given function, see
+/// [`PipelinedRendering3dsPlugin::spawn`].
+pub type RenderThreadSpawner = fn(Box<dyn FnOnce() + Send>) -> std::io::Result<()>;
+
+/// Renders each frame on a dedicated thread while the main world simulates the next one.
+///
+/// At the end of every update the main world is lent to the render thread, which runs
+/// the extract schedule and hands it back before running the [`Render`](bevy::render::Render)
+/// schedule. The main world only waits for the extraction, not for the frame to be drawn.
+///
+/// The render world only ever runs on the render thread, so [`GfxInstance`](crate::GfxInstance)
+/// and [`GpuDevice`](crate::GpuDevice) are created there.
+///
+/// ## Usage
+///
+/// Add it after the default plugins. To render on the system core, start the thread with
+/// `bevy_3ds::system::spawn_on_system_core` and give the app time on that core with
+/// `bevy_3ds::system::set_time_share`.
+pub struct PipelinedRendering3dsPlugin {
+    /// Starts the render thread, a normal thread on the app core by default.
+    pub spawn: RenderThreadSpawner,
+}
+
+impl Default for PipelinedRendering3dsPlugin {
+    fn default() -> Self {
+        Self {
+            spawn: spawn_thread,
+        }
+    }
+}
+
+fn spawn_thread(f: Box<dyn FnOnce() + Send>) -> std::io::Result<()> {
+    std::thread::Builder::new()
+        .name("render".to_owned())
+        .spawn(f)
+        .map(drop)
+}
+
+impl Plugin for PipelinedRendering3dsPlugin {
+    fn build(&self, _app: &mut App) {}
+
+    fn cleanup(&self, app: &mut App) {
+        let Some(render_app) = app.remove_sub_app(RenderApp) else {
+            return;
+        };
+
+        let (send_render_app, receive_render_app) = channel::<SubApp>();
+        let (lend, receive_lent) = channel::<World>();
+        let (give_back, receive_given_back) = channel::<World>();
+        let busy = Arc::new(RenderBusy::default());
+
+        let thread_busy = busy.clone();
+        let spawned = (self.spawn)(Box::new(move || {
+            let Ok(mut render_app) = receive_render_app.recv() else {
+                return;
+            };
+            init_gpu(&mut render_app.app.world);
+
+            render_frames(
+                &receive_lent,
+                &give_back,
+                &thread_busy,
+                &mut render_app,
+                SubApp::extract,
+                SubApp::run,
+            );
+
+            // the gpu has to be closed on this thread, before the main thread is let go
+            drop(render_app);
+            drop(give_back);
+        }));
+
+        if let Err(e) = spawned {
+            log::error!("failed to start the render thread, rendering on the main thread: {e}");
+            app.insert_sub_app(RenderApp, render_app);
+            init_gpu(&mut app.sub_app_mut(RenderApp).world);
+            return;
+        }
+        send_render_app
+            .send(render_app)
+            .expect("the render thread stopped");
+
+        let render_thread = RenderThread {
+            lend: Some(lend),
+            given_back: receive_given_back,
+            _suspend_hook: SuspendHook::hook(busy.clone()),
+            busy,
+        };
+        let mut extract_app = App::empty();
+        extract_app
+            .init_schedule(Main)
+            .init_resource::<ScratchWorld>();
+        app.insert_sub_app(
+            RenderExtractApp,
+            SubApp::new(extract_app, move |main_world, extract_app| {
+                render_thread.lend(main_world, extract_app);
+            }),
+        );
+    }
+}
+
+/// Extracts and renders the lent worlds with the render app until the main thread stops
+/// lending them.
+fn render_frames<R>(
+    receive_lent: &Receiver<World>,
+    give_back: &Sender<World>,
+    busy: &RenderBusy,
+    render_app: &mut R,
+    extract: impl Fn(&mut R, &mut World),
+    render: impl Fn(&mut R),
+) {
+    while let Ok(mut main_world) = receive_lent.recv() {
+        // set here rather than when lending, the next frame can be lent while this one is
+        // drawn and the end of this one would clear it
+        busy.set(true);
+        extract(render_app, &mut main_world);
+        if give_back.send(main_world).is_err() {
+            break;
+        }
+        render(render_app);
+        busy.set(false);
+    }
+}
+
+/// Stands in for the main world while it is lent to the render thread.
+#[derive(Default, Resource)]
+struct ScratchWorld(World);
+
+/// The main thread's end of the render thread.
+struct RenderThread {
+    lend: Option<Sender<World>>,
+    given_back: Receiver<World>,
+    busy: Arc<RenderBusy>,
+    _suspend_hook: Box<SuspendHook>,
+}
+
+impl RenderThread {
+    fn lend(&self, main_world: &mut World, extract_app: &mut App) {
+        let scratch = extract_app.world.remove_resource::<ScratchWorld>().unwrap();
+        let lent = std::mem::replace(main_world, scratch.0);
+
+        // the render thread only takes the world once the previous frame is drawn, and is
+        // busy with it by the time the world is given back
+        self.lend
+            .as_ref()
+            .unwrap()
+            .send(lent)
+            .expect("the render thread stopped");
+        let given_back = self.given_back.recv().expect("the render thread stopped");
+
+        let scratch = std::mem::replace(main_world, given_back);
+        extract_app.world.insert_resource(ScratchWorld(scratch));
+    }
+}
+
+impl Drop for RenderThread {
+    fn drop(&mut self) {
+        // stops the render thread, then waits for it to finish the last frame and close
+        // the gpu, the app shouldn't exit while it is still drawing
+        drop(self.lend.take());
+        while self.given_back.recv().is_ok() {}
+    }
+}
+
+/// Whether the render thread is drawing a frame.
+#[derive(Default)]
+struct RenderBusy {
+    busy: Mutex<bool>,
+    idle: Condvar,
+}
+
+impl RenderBusy {
+    fn set(&self, busy: bool) {
+        *self.busy.lock().unwrap() = busy;
+        if !busy {
+            self.idle.notify_all();
+        }
+    }
+
+    fn wait_until_idle(&self) {
+        let busy = self.busy.lock().unwrap();
+        let _idle = self.idle.wait_while(busy, |busy| *busy).unwrap();
+    }
+}
+
+/// Holds the system off from taking the gpu until the frame being drawn is done.
+///
+/// The main thread is stuck in the APT loop while the app is away, so the render thread
+/// won't get another frame to draw until it is back.
+struct SuspendHook {
+    cookie: ctru_sys::aptHookCookie,
+    busy: Arc<RenderBusy>,
+}
+
+// the cookie is only touched when hooking and unhooking
+unsafe impl Send for SuspendHook {}
+unsafe impl Sync for SuspendHook {}
+
+impl SuspendHook {
+    // boxed so the cookie doesn't move while hooked
+    fn hook(busy: Arc<RenderBusy>) -> Box<Self> {
+        let mut hook = Box::new(Self {
+            cookie: unsafe { std::mem::zeroed() },
+            busy,
+        });
+        let param = Arc::as_ptr(&hook.busy) as *mut c_void;
+        unsafe { ctru_sys::aptHook(&mut hook.cookie, Some(suspend_hook_callback), param) };
+        hook
+    }
+}
+
+impl Drop for SuspendHook {
+    fn drop(&mut self) {
+        unsafe { ctru_sys::aptUnhook(&mut self.cookie) };
+    }
+}
+
+unsafe extern "C" fn suspend_hook_callback(hook: ctru_sys::APT_HookType, param: *mut c_void) {
+    if matches!(
+        hook,
+        ctru_sys::APTHOOK_ONSUSPEND | ctru_sys::APTHOOK_ONSLEEP | ctru_sys::APTHOOK_ONEXIT
+    ) {
+        let busy = &*(param as *const RenderBusy);
+        busy.wait_until_idle();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_busy(busy: &RenderBusy) -> bool {
+        *busy.busy.lock().unwrap()
+    }
+
+    #[test]
+    fn frames_queued_while_drawing_are_busy() {
+        let (lend, receive_lent) = channel::<World>();
+        let (give_back, receive_given_back) = channel::<World>();
+        let (drawing, receive_drawing) = channel::<()>();
+        let (finish, receive_finish) = channel::<()>();
+        let busy = Arc::new(RenderBusy::default());
+
+        let thread_busy = busy.clone();
+        let render_thread = std::thread::spawn(move || {
+            render_frames(
+                &receive_lent,
+                &give_back,
+                &thread_busy,
+                &mut (),
+                |_, _| {},
+                |_| {
+                    drawing.send(()).unwrap();
+                    receive_finish.recv().unwrap();
+                },
+            );
+        });
+
+        lend.send(World::new()).unwrap();
+        receive_given_back.recv().unwrap();
+        receive_drawing.recv().unwrap();
+        assert!(is_busy(&busy));
+
+        // the next frame is lent while the first is still drawn
+        lend.send(World::new()).unwrap();
+        assert!(is_busy(&busy));
+        finish.send(()).unwrap();
+        receive_given_back.recv().unwrap();
+        receive_drawing.recv().unwrap();
+        assert!(is_busy(&busy));
+
+        finish.send(()).unwrap();
+        drop(lend);
+        render_thread.join().unwrap();
+        assert!(!is_busy(&busy));
+    }
+}
@@ -1,12 +1,12 @@
 use std::borrow::{Borrow, BorrowMut};
 use std::cell::{RefCell, RefMut};
 use std::ops::Deref;
-use std::time::Instant;
 
 use bevy::asset::AssetLoader;
 use bevy::core_pipeline::clear_color::ClearColorConfig;
 use bevy::core_pipeline::prepass::{DepthPrepass, NormalPrepass};
 use bevy::ecs::system::SystemState;
+use bevy::input::Axis;
 use bevy::render::camera::ExtractedCamera;
 use bevy::render::extract_component::ExtractComponentPlugin;
 use bevy::render::extract_resource::ExtractResourcePlugin;
@@ -26,6 +26,7 @@ use bevy::{
         MainWorld, Render, RenderApp, RenderSet,
     },
 };
+use bevy_3ds_input::axis::{Axis3ds, Axis3dsType};
 use citro3d::render::{ClearFlags, Target};
 use ctru::services::apt::Apt;
 use ctru::services::gfx::{
@@ -33,6 +34,8 @@ use ctru::services::gfx::{
 };
 
 use crate::lighting::GpuLights;
+use crate::pacing::{FramePacer3ds, FramePacing3ds};
+use crate::pipelined::PipelinedRendering3dsPlugin;
 use crate::{lighting, materials, CameraID, On3dsScreen, RenderOn};
 
 use super::draw::DrawCommands;
@@ -115,7 +118,9 @@ impl Plugin for Render3dsPlugin {
             ExtractComponentPlugin::<On3dsScreen>::default(),
             ExtractComponentPlugin::<CameraID>::default(),
             ExtractComponentPlugin::<RenderOn>::default(),
+            ExtractResourcePlugin::<FramePacing3ds>::default(),
         ));
+        app.init_resource::<FramePacing3ds>();
 
         app.register_type::<color::Color>()
             .register_type::<primitives::Aabb>()
@@ -123,11 +128,49 @@ impl Plugin for Render3dsPlugin {
             .register_type::<primitives::CubemapFrusta>()
             .register_type::<primitives::Frustum>();
     }
+
+    fn finish(&self, app: &mut bevy::prelude::App) {
+        // with pipelined rendering the gpu is opened on the render thread instead
+        if !app.is_plugin_added::<PipelinedRendering3dsPlugin>() {
+            init_gpu(&mut app.sub_app_mut(RenderApp).world);
+        }
+    }
+}
+
+/// Opens the screens and the gpu for the render world.
+///
+/// [`GfxInstance`] can only be used from the thread it was created on, so this has to be
+/// called from the thread that runs the render world.
+pub(crate) fn init_gpu(render_world: &mut World) {
+    render_world.init_resource::<GpuDevice>();
+    render_world.init_non_send_resource::<GfxInstance>();
 }
 
 #[derive(Default, Resource)]
 struct ScratchMainWorld(MainWorld);
 
+/// The position of the 3D depth slider, extracted from the main world's [`Axis<Axis3ds>`]
+///
+/// This is `0.0` (3D off) if the input plugin isn't present, which is logged once
+#[derive(Default, Resource, Clone, Copy, Debug)]
+pub struct ExtractedSlider3d(pub f32);
+
+fn extract_slider_3d(
+    mut commands: Commands,
+    axis: Extract<Option<Res<Axis<Axis3ds>>>>,
+    mut warned: Local<bool>,
+) {
+    if axis.is_none() && !*warned {
+        log::warn!("Axis<Axis3ds> not found, 3d is off without the input plugin");
+        *warned = true;
+    }
+    let value = axis
+        .as_ref()
+        .and_then(|a| a.get(Axis3ds::new(Axis3dsType::Slider3d)))
+        .unwrap_or(0.0);
+    commands.insert_resource(ExtractedSlider3d(value));
+}
+
 fn init_render_app(parent: &mut App) {
     parent.init_resource::<ScratchMainWorld>();
 
@@ -165,15 +208,22 @@ fn init_render_app(parent: &mut App) {
     app.add_schedule(extract_schedule)
         .add_schedule(base_shed)
         .init_resource::<bevy::render::render_graph::RenderGraph>()
-        .init_resource::<GpuDevice>()
         .init_resource::<DrawCommands>()
-        .init_non_send_resource::<GfxInstance>()
+        .init_resource::<ExtractedSlider3d>()
+        .init_resource::<FramePacer3ds>()
         .insert_resource(parent.world.resource::<bevy::asset::AssetServer>().clone())
+        .add_systems(ExtractSchedule, extract_slider_3d)
         .add_systems(
             Render,
             (
                 apply_extract_commands.in_set(RenderSet::ExtractCommands),
+                frame_pacing_system
+                    .in_set(RenderSet::Render)
+                    .before(render_system),
                 render_system.in_set(RenderSet::Render),
+                frame_presented_system
+                    .in_set(RenderSet::Render)
+                    .after(render_system),
                 World::clear_entities.in_set(RenderSet::Cleanup),
             ),
         );
@@ -223,6 +273,39 @@ fn extract(main_world: &mut World, render_app: &mut App) {
     main_world.insert_resource(ScratchMainWorld(inserted_world));
 }
 
+/// Waits for the vblank(s) the frame should be presented on, following [`FramePacing3ds`].
+fn frame_pacing_system(
+    gfx: NonSend<GfxInstance>,
+    pacing: Option<Res<FramePacing3ds>>,
+    pacer: Res<FramePacer3ds>,
+) {
+    let mode = pacing.map(|pacing| pacing.mode).unwrap_or_default();
+    for _ in 0..pacer.vblanks_to_wait(mode, pacer.now()) {
+        gfx.0.wait_for_vblank();
+    }
+}
+
+/// Records the frame [`render_system`] presented and sends its timestamp to the main
+/// world's [`Time`](bevy::time::Time).
+fn frame_presented_system(
+    pacing: Option<Res<FramePacing3ds>>,
+    mut pacer: ResMut<FramePacer3ds>,
+    time_send: Res<TimeSender>,
+) {
+    let mode = pacing.map(|pacing| pacing.mode).unwrap_or_default();
+    let now = pacer.now();
+    // the smoothed vblank time keeps Time's deltas steady
+    let frame_time = pacer.epoch() + pacer.present(mode, now);
+    if let Err(e) = time_send.0.try_send(frame_time) {
+        match e {
+            bevy::time::TrySendError::Full(_) => {
+                panic!("The TimeSender channel should always be empty during render. You might need to add the bevy::core::time_system to your app.",);
+            }
+            bevy::time::TrySendError::Disconnected(_) => {}
+        }
+    }
+}
+
 fn render_system(world: &mut World) {
     log::debug!("render on thread {:?}", std::thread::current().id());
     #[allow(clippy::type_complexity)]
@@ -238,12 +321,12 @@ fn render_system(world: &mut World) {
             Option<&CameraID>,
         )>,
         Res<GpuLights>,
+        Res<ExtractedSlider3d>,
     )> = SystemState::new(world);
-    let (gpu, gfx, commands, clear_colour, cameras, lights) = st.get(world);
+    let (gpu, gfx, commands, clear_colour, cameras, lights, slider) = st.get(world);
     let gpu = gpu.into_inner();
-    gfx.0.wait_for_vblank();
 
-    let slider_val = ctru::os::current_3d_slider_state();
+    let slider_val = slider.0;
 
     //#[allow(clippy::float_cmp)]
     let use_3d = slider_val > 0.0
@@ -422,16 +505,6 @@ fn render_system(world: &mut World) {
     }
 
     log::debug!("render fin");
-
-    let time_send = world.resource::<TimeSender>();
-    if let Err(e) = time_send.0.try_send(Instant::now()) {
-        match e {
-            bevy::time::TrySendError::Full(_) => {
-                panic!("The TimeSender channel should always be empty during render. You might need to add the bevy::core::time_system to your app.",);
-            }
-            bevy::time::TrySendError::Disconnected(_) => {}
-        }
-    }
 }
 
 fn apply_extract_commands(render_world: &mut World) {
@@ -0,0 +1,337 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bevy::asset::io::{AssetReader, AssetReaderError, PathStream, Reader, VecReader};
+use bevy::utils::BoxedFuture;
+
+use crate::io_thread::IoThread;
+use crate::lz11;
+use crate::reader::{get_meta_path, is_meta_file};
+
+pub(crate) const MAGIC: [u8; 4] = *b"3PAK";
+pub(crate) const VERSION: u32 = 1;
+
+/// How an entry of an asset archive is stored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// LZ11, as used by Nintendo's 3ds tooling.
+    Lz11,
+}
+
+impl Compression {
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Lz11 => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> io::Result<Self> {
+        match byte {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Lz11),
+            _ => Err(invalid_data("unknown compression")),
+        }
+    }
+}
+
+pub(crate) fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid asset archive: {message}"),
+    )
+}
+
+/// Reads assets from an archive built with [`ArchiveBuilder`](crate::ArchiveBuilder), e.g.
+/// with the `pack_assets` binary
+///
+/// The archive is a single file with an index of the assets followed by their data, each
+/// compressed on its own. Asset paths are the paths the assets were packed with, so
+/// packing `romfs/assets` keeps the same paths as loading from it.
+///
+/// The index is read once when the archive is opened, assets are read and decompressed
+/// on the io thread when they are loaded.
+#[derive(Clone)]
+pub struct ArchiveAssetReader {
+    archive: Arc<Archive>,
+}
+
+struct Archive {
+    path: PathBuf,
+    entries: HashMap<PathBuf, Entry>,
+    /// The assets and directories in each directory, the root being the empty path.
+    directories: HashMap<PathBuf, Vec<PathBuf>>,
+}
+
+struct Entry {
+    compression: Compression,
+    offset: u32,
+    stored_len: u32,
+    len: u32,
+}
+
+impl ArchiveAssetReader {
+    /// Opens the archive at `path`, e.g. `romfs:/assets.pak`.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let mut file = BufReader::new(File::open(&path)?);
+
+        let mut magic = [0; 4];
+        file.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(invalid_data("not an asset archive"));
+        }
+        if read_u32(&mut file)? != VERSION {
+            return Err(invalid_data("unsupported version"));
+        }
+
+        let count = read_u32(&mut file)?;
+        let mut entries = HashMap::with_capacity(count as usize);
+        for _ in 0..count {
+            let mut name = vec![0; read_u16(&mut file)? as usize];
+            file.read_exact(&mut name)?;
+            let name = String::from_utf8(name).map_err(|_| invalid_data("path isn't utf-8"))?;
+            let mut compression = [0];
+            file.read_exact(&mut compression)?;
+            let entry = Entry {
+                compression: Compression::from_byte(compression[0])?,
+                offset: read_u32(&mut file)?,
+                stored_len: read_u32(&mut file)?,
+                len: read_u32(&mut file)?,
+            };
+            entries.insert(PathBuf::from(name), entry);
+        }
+
+        let mut directories: HashMap<PathBuf, BTreeSet<PathBuf>> = HashMap::new();
+        directories.entry(PathBuf::new()).or_default();
+        for path in entries.keys().filter(|path| !is_meta_file(path)) {
+            let mut child = path.as_path();
+            while let Some(parent) = child.parent() {
+                directories
+                    .entry(parent.to_owned())
+                    .or_default()
+                    .insert(child.to_owned());
+                child = parent;
+            }
+        }
+
+        Ok(Self {
+            archive: Arc::new(Archive {
+                path,
+                entries,
+                directories: directories
+                    .into_iter()
+                    .map(|(directory, children)| (directory, children.into_iter().collect()))
+                    .collect(),
+            }),
+        })
+    }
+
+    /// The path of the archive file.
+    pub fn path(&self) -> &Path {
+        &self.archive.path
+    }
+
+    fn read_entry<'a>(
+        &'a self,
+        path: PathBuf,
+    ) -> BoxedFuture<'a, Result<Box<Reader<'a>>, AssetReaderError>> {
+        let archive = self.archive.clone();
+        Box::pin(async move {
+            let bytes = IoThread::get()
+                .run_fallible(move || {
+                    let Some(entry) = archive.entries.get(&path) else {
+                        return Err(AssetReaderError::NotFound(path));
+                    };
+                    archive.read(entry).map_err(|e| {
+                        log::error!("failed to read {:#?} from {:#?}: {e}", path, archive.path);
+                        AssetReaderError::Io(e)
+                    })
+                })
+                .await?;
+            Ok(Box::new(VecReader::new(bytes)) as Box<Reader>)
+        })
+    }
+}
+
+impl Archive {
+    fn read(&self, entry: &Entry) -> io::Result<Vec<u8>> {
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(entry.offset.into()))?;
+        let mut stored = vec![0; entry.stored_len as usize];
+        file.read_exact(&mut stored)?;
+
+        let bytes = match entry.compression {
+            Compression::None => stored,
+            Compression::Lz11 => lz11::decompress(&stored)?,
+        };
+        if bytes.len() != entry.len as usize {
+            return Err(invalid_data("entry has the wrong size"));
+        }
+        Ok(bytes)
+    }
+}
+
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+impl AssetReader for ArchiveAssetReader {
+    fn read<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<Reader<'a>>, AssetReaderError>> {
+        self.read_entry(path.to_owned())
+    }
+
+    fn read_meta<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<Reader<'a>>, AssetReaderError>> {
+        self.read_entry(get_meta_path(path))
+    }
+
+    fn read_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<PathStream>, AssetReaderError>> {
+        Box::pin(async move {
+            let Some(children) = self.archive.directories.get(path) else {
+                return Err(AssetReaderError::NotFound(path.to_owned()));
+            };
+            Ok(Box::new(futures::stream::iter(children.clone())) as Box<PathStream>)
+        })
+    }
+
+    fn is_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<bool, AssetReaderError>> {
+        Box::pin(async move {
+            if self.archive.directories.contains_key(path) {
+                Ok(true)
+            } else if self.archive.entries.contains_key(path) {
+                Ok(false)
+            } else {
+                Err(AssetReaderError::NotFound(path.to_owned()))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{is_directory, is_not_found, read, read_directory, read_meta, TempDir};
+    use crate::ArchiveBuilder;
+
+    fn write(builder: &ArchiveBuilder, dir: &TempDir) -> ArchiveAssetReader {
+        let path = dir.path().join("assets.pak");
+        builder.write(File::create(&path).unwrap()).unwrap();
+        ArchiveAssetReader::open(path).unwrap()
+    }
+
+    #[test]
+    fn assets_round_trip_through_the_archive() {
+        let dir = TempDir::new();
+        let repetitive = b"tile".repeat(1000);
+        let mut builder = ArchiveBuilder::new();
+        builder
+            .add("sprite.png", b"sprite", Compression::None)
+            .unwrap();
+        builder
+            .add("sprite.png.meta", b"meta", Compression::Lz11)
+            .unwrap();
+        builder
+            .add("levels/1.ron", &repetitive, Compression::Lz11)
+            .unwrap();
+        builder.add("empty.txt", b"", Compression::Lz11).unwrap();
+        let archive = write(&builder, &dir);
+
+        assert_eq!(read(&archive, "sprite.png").unwrap(), b"sprite");
+        assert_eq!(read_meta(&archive, "sprite.png").unwrap(), b"meta");
+        assert_eq!(read(&archive, "levels/1.ron").unwrap(), repetitive);
+        assert_eq!(read(&archive, "empty.txt").unwrap(), b"");
+        assert!(is_not_found(read(&archive, "missing.png")));
+        assert!(is_not_found(read_meta(&archive, "levels/1.ron")));
+    }
+
+    #[test]
+    fn directories_are_built_from_the_entries() {
+        let dir = TempDir::new();
+        let mut builder = ArchiveBuilder::new();
+        for path in [
+            "a.png",
+            "a.png.meta",
+            "sprites/b.png",
+            "sprites/enemies/c.png",
+        ] {
+            builder.add(path, b"asset", Compression::None).unwrap();
+        }
+        let archive = write(&builder, &dir);
+
+        assert_eq!(
+            read_directory(&archive, "").unwrap(),
+            [PathBuf::from("a.png"), PathBuf::from("sprites")]
+        );
+        assert_eq!(
+            read_directory(&archive, "sprites").unwrap(),
+            [
+                PathBuf::from("sprites/b.png"),
+                PathBuf::from("sprites/enemies"),
+            ]
+        );
+        assert!(is_directory(&archive, "sprites/enemies").unwrap());
+        assert!(!is_directory(&archive, "sprites/b.png").unwrap());
+        assert!(is_not_found(read_directory(&archive, "missing")));
+        assert!(is_not_found(is_directory(&archive, "missing")));
+    }
+
+    #[test]
+    fn directories_are_packed_with_relative_paths() {
+        let (assets, dir) = (TempDir::new(), TempDir::new());
+        assets.write("sprite.png", b"sprite");
+        assets.write("sounds/jump.wav", b"jump".repeat(100));
+        let mut builder = ArchiveBuilder::new();
+        builder
+            .add_directory(assets.path(), Compression::Lz11)
+            .unwrap();
+        assert_eq!(builder.len(), 2);
+        let archive = write(&builder, &dir);
+
+        assert_eq!(read(&archive, "sprite.png").unwrap(), b"sprite");
+        assert_eq!(
+            read(&archive, "sounds/jump.wav").unwrap(),
+            b"jump".repeat(100)
+        );
+    }
+
+    #[test]
+    fn invalid_archives_are_not_opened() {
+        let dir = TempDir::new();
+        dir.write("not_an_archive.pak", b"not an archive");
+        let error = ArchiveAssetReader::open(dir.path().join("not_an_archive.pak"))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let error = ArchiveAssetReader::open(dir.path().join("missing.pak"))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+}
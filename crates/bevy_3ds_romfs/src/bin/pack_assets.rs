@@ -0,0 +1,49 @@
+//! Packs a directory of assets into an archive for `RomfsAssetPlugin::archive`
+//!
+//! `cargo run -p bevy_3ds_romfs --bin pack_assets -- [assets directory] [archive]`, which
+//! packs `romfs/assets` into `assets.pak` by default. The archive then goes in the romfs
+//! in place of the assets directory.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use bevy_3ds_romfs::{ArchiveBuilder, Compression};
+
+fn main() -> ExitCode {
+    let mut args = std::env::args_os().skip(1);
+    let assets = args
+        .next()
+        .map_or_else(|| "romfs/assets".into(), PathBuf::from);
+    let archive = args
+        .next()
+        .map_or_else(|| "assets.pak".into(), PathBuf::from);
+
+    match pack(&assets, &archive) {
+        Ok(count) => {
+            println!("packed {count} assets into {}", archive.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!(
+                "failed to pack {} into {}: {e}",
+                assets.display(),
+                archive.display()
+            );
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn pack(assets: &Path, archive: &Path) -> std::io::Result<usize> {
+    let mut builder = ArchiveBuilder::new();
+    builder.add_directory(assets, Compression::Lz11)?;
+
+    let mut file = BufWriter::new(File::create(archive)?);
+    builder.write(&mut file)?;
+    file.flush()?;
+    Ok(builder.len())
+}
@@ -0,0 +1,70 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::OnceLock;
+
+use futures::channel::oneshot;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A thread that does the blocking file operations for the asset readers and writers, one
+/// at a time and in the order they were sent.
+///
+/// Blocking in an asset future stalls the task pool thread polling it, which is often
+/// the main thread on the 3ds. Work sent here completes the future through its waker
+/// instead.
+pub(crate) struct IoThread {
+    jobs: Sender<Job>,
+}
+
+impl IoThread {
+    /// The thread shared by all readers and writers, started on first use.
+    pub(crate) fn get() -> &'static IoThread {
+        static IO_THREAD: OnceLock<IoThread> = OnceLock::new();
+        IO_THREAD.get_or_init(|| {
+            let (jobs, receiver) = channel::<Job>();
+            std::thread::Builder::new()
+                .name("romfs io".to_owned())
+                .spawn(move || {
+                    while let Ok(job) = receiver.recv() {
+                        // a panicking job only loses its own result
+                        let _ = catch_unwind(AssertUnwindSafe(job));
+                    }
+                })
+                .expect("failed to start the romfs io thread");
+            IoThread { jobs }
+        })
+    }
+
+    /// Runs `f` on the thread, the receiver completes with its result.
+    pub(crate) fn run<T: Send + 'static>(
+        &self,
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> oneshot::Receiver<T> {
+        let (sender, receiver) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            // the future may have been dropped in the meantime
+            let _ = sender.send(f());
+        });
+        // the thread never stops, as the sender is never dropped
+        let _ = self.jobs.send(job);
+        receiver
+    }
+
+    /// Runs `f` on the thread and waits for it without blocking.
+    pub(crate) async fn run_fallible<
+        T: Send + 'static,
+        E: From<std::io::Error> + Send + 'static,
+    >(
+        &self,
+        f: impl FnOnce() -> Result<T, E> + Send + 'static,
+    ) -> Result<T, E> {
+        self.run(f)
+            .await
+            .unwrap_or_else(|_| Err(lost_error().into()))
+    }
+}
+
+/// The error for work that panicked on the thread.
+pub(crate) fn lost_error() -> std::io::Error {
+    std::io::Error::other("a romfs io operation panicked")
+}
@@ -1,22 +1,190 @@
+use std::path::PathBuf;
+
 use bevy::{
     app::Plugin,
     asset::{
-        io::{AssetSource, AssetSourceBuilder, AssetSourceId},
+        io::{AssetReader, AssetSource, AssetSourceId},
         AssetApp,
     },
 };
 
-use self::reader::RomfsAssetReader;
+pub use self::archive::{ArchiveAssetReader, Compression};
+pub use self::overlay::OverlayAssetReader;
+pub use self::pack::ArchiveBuilder;
+pub use self::reader::RomfsAssetReader;
+pub use self::writer::SdAssetWriter;
 
+mod archive;
+mod io_thread;
+mod lz11;
+mod overlay;
+mod pack;
 mod reader;
+#[cfg(test)]
+mod test_util;
+mod writer;
+
+/// Loads assets from the romfs, and from the SD card through the `sdmc` asset source,
+/// e.g. `asset_server.load("sdmc://saves/level.ron")`. Assets can also be saved to the
+/// SD card through the `sdmc` source.
+///
+/// The romfs assets can also be packed into a compressed archive to keep the romfs small,
+/// see [`RomfsAssetPlugin::archive`].
+#[derive(Clone)]
+pub struct RomfsAssetPlugin {
+    /// The directory the default asset source reads from, `romfs:/` by default.
+    pub root: PathBuf,
+    /// A directory on the SD card to look assets up in before the romfs, e.g.
+    /// `sdmc:/3ds/<game>/assets`, so assets can be patched without rebuilding the game.
+    /// `None` only uses the romfs.
+    pub overlay: Option<PathBuf>,
+    /// A directory on the SD card to cache processed assets in, e.g.
+    /// `sdmc:/3ds/<game>/processed`. With bevy's asset processor running, assets are
+    /// processed on the first run and loaded from the cache afterwards.
+    ///
+    /// The processor keeps its log in `imported_assets/log` under `BEVY_ASSET_ROOT`, which
+    /// is the read-only romfs on the 3ds, so the root is moved to the parent of this
+    /// directory, e.g. `sdmc:/3ds/<game>/imported_assets/log`.
+    pub processed: Option<PathBuf>,
+    /// An asset archive to load assets from instead of the romfs, e.g. `romfs:/assets.pak`,
+    /// packed from the assets directory with the `pack_assets` binary. The archive's index
+    /// is read when the plugin is built, if it can't be opened assets are loaded from the
+    /// root.
+    pub archive: Option<PathBuf>,
+    /// The number of bytes read from a file at a time, see [`RomfsAssetReader::with_chunk_size`].
+    pub read_chunk_size: usize,
+}
 
-pub struct RomfsAssetPlugin;
+impl Default for RomfsAssetPlugin {
+    fn default() -> Self {
+        Self {
+            root: "romfs:/".into(),
+            overlay: None,
+            processed: None,
+            archive: None,
+            read_chunk_size: RomfsAssetReader::DEFAULT_CHUNK_SIZE,
+        }
+    }
+}
 
 impl Plugin for RomfsAssetPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.register_asset_source(
-            AssetSourceId::Default,
-            AssetSource::build().with_reader(|| Box::new(RomfsAssetReader)),
+        let chunk_size = self.read_chunk_size;
+        let reader = move |root: PathBuf| RomfsAssetReader::new(root).with_chunk_size(chunk_size);
+
+        let archive = self
+            .archive
+            .as_ref()
+            .and_then(|path| match ArchiveAssetReader::open(path) {
+                Ok(archive) => Some(archive),
+                Err(e) => {
+                    log::error!(
+                        "failed to open the asset archive {path:#?}, loading assets from the \
+                         root instead: {e}"
+                    );
+                    None
+                }
+            });
+        let (root, overlay) = (self.root.clone(), self.overlay.clone());
+        let mut source = AssetSource::build().with_reader(move || match (&overlay, &archive) {
+            (Some(overlay), Some(archive)) => Box::new(OverlayAssetReader::new(
+                reader(overlay.clone()),
+                archive.clone(),
+            )) as Box<dyn AssetReader>,
+            (Some(overlay), None) => Box::new(OverlayAssetReader::new(
+                reader(overlay.clone()),
+                reader(root.clone()),
+            )),
+            (None, Some(archive)) => Box::new(archive.clone()),
+            (None, None) => Box::new(reader(root.clone())),
+        });
+        if let Some(processed) = &self.processed {
+            let (reader_path, writer_path) = (processed.clone(), processed.clone());
+            source = source
+                .with_processed_reader(move || Box::new(reader(reader_path.clone())))
+                .with_processed_writer(move || {
+                    // the processor clears its processed assets to start over
+                    Some(Box::new(
+                        SdAssetWriter::new(writer_path.clone()).allow_clearing_root(),
+                    ))
+                });
+        }
+
+        app.register_asset_source(AssetSourceId::Default, source)
+            .register_asset_source(
+                "sdmc",
+                AssetSource::build()
+                    .with_reader(move || Box::new(reader("sdmc:/".into())))
+                    .with_writer(|| Some(Box::<SdAssetWriter>::default())),
+            );
+    }
+
+    fn finish(&self, _app: &mut bevy::prelude::App) {
+        // after the other plugins' builds, `Core3dsPlugin` sets the root to the romfs
+        let Some(processed) = &self.processed else {
+            return;
+        };
+        match processed.parent() {
+            Some(root) if !root.as_os_str().is_empty() => {
+                std::env::set_var("BEVY_ASSET_ROOT", root);
+            }
+            _ => log::error!(
+                "the processed asset directory {processed:#?} has no parent to keep the \
+                 asset processor's log in"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        app::App,
+        asset::{AssetPlugin, AssetServer},
+    };
+
+    use super::*;
+    use crate::test_util::{read, TempDir};
+
+    #[test]
+    fn missing_archives_fall_back_to_the_root() {
+        let dir = TempDir::new();
+        dir.write("level.ron", b"level");
+        let mut app = App::new();
+        app.add_plugins((
+            RomfsAssetPlugin {
+                root: dir.path().to_owned(),
+                archive: Some(dir.path().join("missing.pak")),
+                ..Default::default()
+            },
+            AssetPlugin::default(),
+        ));
+
+        let source = app
+            .world
+            .resource::<AssetServer>()
+            .get_source(AssetSourceId::Default)
+            .unwrap();
+        assert_eq!(read(source.reader(), "level.ron").unwrap(), b"level");
+    }
+
+    #[test]
+    fn the_asset_root_is_moved_next_to_the_processed_assets() {
+        let dir = TempDir::new();
+        let mut app = App::new();
+        app.add_plugins((
+            RomfsAssetPlugin {
+                processed: Some(dir.path().join("processed")),
+                ..Default::default()
+            },
+            AssetPlugin::default(),
+        ));
+        std::env::set_var("BEVY_ASSET_ROOT", "romfs:/");
+        app.finish();
+
+        assert_eq!(
+            std::env::var_os("BEVY_ASSET_ROOT").unwrap(),
+            dir.path().as_os_str()
         );
     }
 }
@@ -0,0 +1,272 @@
+//! The LZ11 compression used by Nintendo's 3ds tooling.
+//!
+//! The data starts with a header of `0x11` and the decompressed size, then groups of up to
+//! 8 tokens, each group led by a flag byte with a bit per token, most significant first.
+//! Tokens are either a literal byte (bit clear) or a copy of earlier output (bit set) of 3
+//! to 0x10110 bytes from up to 0x1000 bytes back.
+
+use std::io;
+
+const TYPE: u8 = 0x11;
+const WINDOW: usize = 0x1000;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 0x10110;
+/// How many earlier positions with the same prefix are tried for a match.
+const MAX_CHAIN: usize = 128;
+const HASH_BITS: u32 = 15;
+const NONE: u32 = u32::MAX;
+
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    let len = u32::try_from(data.len()).expect("lz11 data is limited to 4 GiB");
+    let mut out = Vec::with_capacity(data.len() / 2 + 8);
+    if (1..=0xff_ffff).contains(&len) {
+        out.extend_from_slice(&(len << 8 | TYPE as u32).to_le_bytes());
+    } else {
+        // a size of 0 means the real size follows, so empty data needs it too
+        out.extend_from_slice(&[TYPE, 0, 0, 0]);
+        out.extend_from_slice(&len.to_le_bytes());
+    }
+
+    let mut matcher = Matcher::new(data.len());
+    let mut pos = 0;
+    while pos < data.len() {
+        let flags = out.len();
+        out.push(0);
+        for bit in 0..8 {
+            if pos >= data.len() {
+                break;
+            }
+            match matcher.longest_match(data, pos) {
+                Some((len, distance)) => {
+                    out[flags] |= 0x80 >> bit;
+                    push_copy(&mut out, len, distance);
+                    for p in pos..pos + len {
+                        matcher.insert(data, p);
+                    }
+                    pos += len;
+                }
+                None => {
+                    out.push(data[pos]);
+                    matcher.insert(data, pos);
+                    pos += 1;
+                }
+            }
+        }
+    }
+    out
+}
+
+fn push_copy(out: &mut Vec<u8>, len: usize, distance: usize) {
+    let d = distance - 1;
+    if len <= 0x10 {
+        out.extend_from_slice(&[((len - 1) << 4 | d >> 8) as u8, d as u8]);
+    } else if len <= 0x110 {
+        let l = len - 0x11;
+        out.extend_from_slice(&[(l >> 4) as u8, ((l & 0xf) << 4 | d >> 8) as u8, d as u8]);
+    } else {
+        let l = len - 0x111;
+        out.extend_from_slice(&[
+            (0x10 | l >> 12) as u8,
+            (l >> 4) as u8,
+            ((l & 0xf) << 4 | d >> 8) as u8,
+            d as u8,
+        ]);
+    }
+}
+
+/// Finds earlier occurrences of the bytes at a position through chains of the positions
+/// sharing their first 3 bytes.
+struct Matcher {
+    head: Vec<u32>,
+    previous: Vec<u32>,
+}
+
+impl Matcher {
+    fn new(len: usize) -> Self {
+        Self {
+            head: vec![NONE; 1 << HASH_BITS],
+            previous: vec![NONE; len],
+        }
+    }
+
+    fn hash(data: &[u8], pos: usize) -> usize {
+        let prefix = u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], 0]);
+        (prefix.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+    }
+
+    fn insert(&mut self, data: &[u8], pos: usize) {
+        if pos + MIN_MATCH > data.len() {
+            return;
+        }
+        let hash = Self::hash(data, pos);
+        self.previous[pos] = self.head[hash];
+        self.head[hash] = pos as u32;
+    }
+
+    /// The length and distance of the longest match for `pos`, if there is one.
+    fn longest_match(&self, data: &[u8], pos: usize) -> Option<(usize, usize)> {
+        if pos + MIN_MATCH > data.len() {
+            return None;
+        }
+        let max_len = MAX_MATCH.min(data.len() - pos);
+        let mut best: Option<(usize, usize)> = None;
+        let mut candidate = self.head[Self::hash(data, pos)];
+        for _ in 0..MAX_CHAIN {
+            if candidate == NONE || pos - candidate as usize > WINDOW {
+                break;
+            }
+            let start = candidate as usize;
+            let len = data[start..]
+                .iter()
+                .zip(&data[pos..pos + max_len])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if len >= MIN_MATCH && len > best.map_or(0, |(best_len, _)| best_len) {
+                best = Some((len, pos - start));
+                if len == max_len {
+                    break;
+                }
+            }
+            candidate = self.previous[start];
+        }
+        best
+    }
+}
+
+pub(crate) fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid lz11 data");
+    let mut bytes = data.iter().copied();
+    let mut next = || bytes.next().ok_or_else(invalid);
+
+    if next()? != TYPE {
+        return Err(invalid());
+    }
+    let mut len = u32::from_le_bytes([next()?, next()?, next()?, 0]);
+    if len == 0 {
+        len = u32::from_le_bytes([next()?, next()?, next()?, next()?]);
+    }
+    let len = len as usize;
+
+    let mut out = Vec::with_capacity(len);
+    while out.len() < len {
+        let flags = next()?;
+        for bit in 0..8 {
+            if out.len() >= len {
+                break;
+            }
+            if flags & (0x80 >> bit) == 0 {
+                out.push(next()?);
+                continue;
+            }
+            let b0 = next()? as usize;
+            let b1 = next()? as usize;
+            let (copy_len, distance) = match b0 >> 4 {
+                0 => {
+                    let b2 = next()? as usize;
+                    ((b0 << 4 | b1 >> 4) + 0x11, ((b1 & 0xf) << 8 | b2) + 1)
+                }
+                1 => {
+                    let b2 = next()? as usize;
+                    let b3 = next()? as usize;
+                    (
+                        ((b0 & 0xf) << 12 | b1 << 4 | b2 >> 4) + 0x111,
+                        ((b2 & 0xf) << 8 | b3) + 1,
+                    )
+                }
+                n => (n + 1, ((b0 & 0xf) << 8 | b1) + 1),
+            };
+            if distance > out.len() || out.len() + copy_len > len {
+                return Err(invalid());
+            }
+            // the copy can overlap the bytes it produces
+            let start = out.len() - distance;
+            for i in start..start + copy_len {
+                out.push(out[i]);
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(data: &[u8]) -> Vec<u8> {
+        let compressed = compress(data);
+        assert_eq!(decompress(&compressed).unwrap(), data);
+        compressed
+    }
+
+    /// Bytes without repeats for the matcher to find.
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x1234_5678u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty_data_is_just_the_4_byte_size() {
+        assert_eq!(round_trip(&[]), [TYPE, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn short_data_is_stored_as_literals() {
+        for len in 1..=MIN_MATCH + 1 {
+            let data = noise(len);
+            let compressed = round_trip(&data);
+            // the header, a flag byte and the literals
+            assert_eq!(compressed.len(), 4 + 1 + len);
+        }
+        round_trip(b"abcabcabcabc");
+        round_trip(&noise(1000));
+    }
+
+    #[test]
+    fn repetitive_data_compresses_well() {
+        let zeros = vec![0; 0x10_0000];
+        assert!(round_trip(&zeros).len() < 0x100);
+
+        let mut pattern = noise(100).repeat(1000);
+        pattern.extend(noise(50));
+        assert!(round_trip(&pattern).len() < pattern.len() / 20);
+    }
+
+    #[test]
+    fn copies_of_every_length_encoding_round_trip() {
+        // lengths at the edges of the 2, 3 and 4 byte copies
+        for len in [3, 0x10, 0x11, 0x110, 0x111, MAX_MATCH, MAX_MATCH + 1] {
+            let mut data = noise(16);
+            data.resize(data.len() + len, 0xaa);
+            data.extend(noise(16));
+            round_trip(&data);
+        }
+    }
+
+    #[test]
+    fn large_data_has_a_4_byte_size() {
+        let mut data = noise(WINDOW).repeat(0x100_0000 / WINDOW);
+        data.extend(noise(0x123));
+        let compressed = round_trip(&data);
+        assert_eq!(compressed[..4], [TYPE, 0, 0, 0]);
+        assert_eq!(compressed[4..8], (data.len() as u32).to_le_bytes());
+    }
+
+    #[test]
+    fn invalid_data_is_an_error() {
+        let compressed = compress(b"abcabcabcabc");
+        let invalid =
+            |data: &[u8]| decompress(data).unwrap_err().kind() == io::ErrorKind::InvalidData;
+        assert!(invalid(&[]));
+        assert!(invalid(&[0x10, 1, 0, 0, 0, 0]));
+        assert!(invalid(&compressed[..compressed.len() - 1]));
+        // a copy from before the start of the data
+        assert!(invalid(&[TYPE, 3, 0, 0, 0x80, 0x20, 0x00]));
+    }
+}
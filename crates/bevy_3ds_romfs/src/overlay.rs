@@ -0,0 +1,177 @@
+use std::path::Path;
+
+use bevy::asset::io::{AssetReader, AssetReaderError, PathStream, Reader};
+use bevy::utils::BoxedFuture;
+use futures::StreamExt;
+
+/// Layers one [`AssetReader`] over another, assets are looked up in `top` first and
+/// then in `bottom` if `top` doesn't have them.
+///
+/// Meta files are looked up the same way, separately from their assets. Directories list
+/// the assets of both layers.
+pub struct OverlayAssetReader<T, B> {
+    top: T,
+    bottom: B,
+}
+
+impl<T: AssetReader, B: AssetReader> OverlayAssetReader<T, B> {
+    pub fn new(top: T, bottom: B) -> Self {
+        Self { top, bottom }
+    }
+}
+
+impl<T: AssetReader, B: AssetReader> AssetReader for OverlayAssetReader<T, B> {
+    fn read<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<Reader<'a>>, AssetReaderError>> {
+        Box::pin(async move {
+            match self.top.read(path).await {
+                Err(AssetReaderError::NotFound(_)) => self.bottom.read(path).await,
+                result => result,
+            }
+        })
+    }
+
+    fn read_meta<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<Reader<'a>>, AssetReaderError>> {
+        Box::pin(async move {
+            match self.top.read_meta(path).await {
+                Err(AssetReaderError::NotFound(_)) => self.bottom.read_meta(path).await,
+                result => result,
+            }
+        })
+    }
+
+    fn read_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<PathStream>, AssetReaderError>> {
+        Box::pin(async move {
+            let mut found = false;
+            let mut paths = Vec::new();
+            for layer in [
+                self.top.read_directory(path).await,
+                self.bottom.read_directory(path).await,
+            ] {
+                match layer {
+                    Ok(stream) => {
+                        found = true;
+                        paths.extend(stream.collect::<Vec<_>>().await);
+                    }
+                    Err(AssetReaderError::NotFound(_)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            if !found {
+                return Err(AssetReaderError::NotFound(path.to_owned()));
+            }
+
+            // assets in both layers are only listed once
+            paths.sort();
+            paths.dedup();
+            Ok(Box::new(futures::stream::iter(paths)) as Box<PathStream>)
+        })
+    }
+
+    fn is_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<bool, AssetReaderError>> {
+        Box::pin(async move {
+            match self.top.is_directory(path).await {
+                Err(AssetReaderError::NotFound(_)) => self.bottom.is_directory(path).await,
+                result => result,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::test_util::{is_directory, is_not_found, read, read_directory, read_meta, TempDir};
+    use crate::RomfsAssetReader;
+
+    /// An overlay of two host directories, `top` patching `bottom`.
+    fn overlay(
+        top: &TempDir,
+        bottom: &TempDir,
+    ) -> OverlayAssetReader<RomfsAssetReader, RomfsAssetReader> {
+        OverlayAssetReader::new(
+            RomfsAssetReader::new(top.path()),
+            RomfsAssetReader::new(bottom.path()),
+        )
+    }
+
+    #[test]
+    fn assets_are_read_from_the_top_first() {
+        let (top, bottom) = (TempDir::new(), TempDir::new());
+        top.write("patched.png", b"top");
+        bottom.write("patched.png", b"bottom");
+        bottom.write("original.png", b"bottom");
+        let reader = overlay(&top, &bottom);
+
+        assert_eq!(read(&reader, "patched.png").unwrap(), b"top");
+        assert_eq!(read(&reader, "original.png").unwrap(), b"bottom");
+        assert!(is_not_found(read(&reader, "missing.png")));
+    }
+
+    #[test]
+    fn meta_files_are_looked_up_separately() {
+        let (top, bottom) = (TempDir::new(), TempDir::new());
+        top.write("sprite.png", b"top");
+        bottom.write("sprite.png", b"bottom");
+        bottom.write("sprite.png.meta", b"bottom meta");
+        let reader = overlay(&top, &bottom);
+
+        assert_eq!(read(&reader, "sprite.png").unwrap(), b"top");
+        assert_eq!(read_meta(&reader, "sprite.png").unwrap(), b"bottom meta");
+
+        top.write("sprite.png.meta", b"top meta");
+        assert_eq!(read_meta(&reader, "sprite.png").unwrap(), b"top meta");
+    }
+
+    #[test]
+    fn directories_list_both_layers_once() {
+        let (top, bottom) = (TempDir::new(), TempDir::new());
+        top.write("sprites/a.png", b"top");
+        top.write("sprites/new.png", b"top");
+        bottom.write("sprites/a.png", b"bottom");
+        bottom.write("sprites/b.png", b"bottom");
+        bottom.write("sounds/jump.wav", b"bottom");
+        let reader = overlay(&top, &bottom);
+
+        assert_eq!(
+            read_directory(&reader, "sprites").unwrap(),
+            [
+                PathBuf::from("sprites/a.png"),
+                PathBuf::from("sprites/b.png"),
+                PathBuf::from("sprites/new.png"),
+            ]
+        );
+        // only in the bottom layer
+        assert_eq!(
+            read_directory(&reader, "sounds").unwrap(),
+            [PathBuf::from("sounds/jump.wav")]
+        );
+        assert!(is_not_found(read_directory(&reader, "missing")));
+    }
+
+    #[test]
+    fn directories_are_looked_up_in_both_layers() {
+        let (top, bottom) = (TempDir::new(), TempDir::new());
+        top.write("levels/1.ron", b"top");
+        bottom.write("sounds/jump.wav", b"bottom");
+        let reader = overlay(&top, &bottom);
+
+        assert!(is_directory(&reader, "levels").unwrap());
+        assert!(is_directory(&reader, "sounds").unwrap());
+        assert!(!is_directory(&reader, "sounds/jump.wav").unwrap());
+        assert!(is_not_found(is_directory(&reader, "missing")));
+    }
+}
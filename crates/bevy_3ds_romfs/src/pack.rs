@@ -0,0 +1,141 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::{Component, Path},
+};
+
+use crate::archive::{invalid_data, Compression, MAGIC, VERSION};
+use crate::lz11;
+
+/// Builds an asset archive for [`ArchiveAssetReader`](crate::ArchiveAssetReader)
+///
+/// Entries are written in path order, so packing the same assets gives the same archive.
+#[derive(Default)]
+pub struct ArchiveBuilder {
+    entries: BTreeMap<String, PackedEntry>,
+}
+
+struct PackedEntry {
+    compression: Compression,
+    data: Vec<u8>,
+    len: u32,
+}
+
+impl ArchiveBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an asset at `path`, relative to the root of the archive. The asset is stored
+    /// uncompressed if `compression` doesn't make it smaller.
+    pub fn add(
+        &mut self,
+        path: impl AsRef<Path>,
+        data: &[u8],
+        compression: Compression,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        let name = archive_path(path)
+            .ok_or_else(|| invalid_data(&format!("{path:?} isn't a relative utf-8 path")))?;
+        if self.entries.contains_key(&name) {
+            return Err(invalid_data(&format!("{path:?} was already added")));
+        }
+        let len = u32::try_from(data.len())
+            .map_err(|_| invalid_data(&format!("{path:?} is larger than 4 GiB")))?;
+
+        let compressed = match compression {
+            Compression::None => None,
+            Compression::Lz11 => Some(lz11::compress(data)),
+        };
+        let entry = match compressed {
+            Some(compressed) if compressed.len() < data.len() => PackedEntry {
+                compression,
+                data: compressed,
+                len,
+            },
+            _ => PackedEntry {
+                compression: Compression::None,
+                data: data.to_vec(),
+                len,
+            },
+        };
+        self.entries.insert(name, entry);
+        Ok(())
+    }
+
+    /// Adds every file under `directory`, with paths relative to it.
+    pub fn add_directory(
+        &mut self,
+        directory: impl AsRef<Path>,
+        compression: Compression,
+    ) -> io::Result<()> {
+        let directory = directory.as_ref();
+        let mut pending = vec![directory.to_owned()];
+        while let Some(current) = pending.pop() {
+            for entry in std::fs::read_dir(&current)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else {
+                    let data = std::fs::read(&path)?;
+                    let relative = path
+                        .strip_prefix(directory)
+                        .expect("read_dir returns paths in the directory");
+                    self.add(relative, &data, compression)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of assets added.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Writes the archive.
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        let too_large = || invalid_data("the archive is larger than 4 GiB");
+        let index_len: usize = self
+            .entries
+            .keys()
+            .map(|name| 2 + name.len() + 1 + 3 * 4)
+            .sum();
+        let mut offset = u32::try_from(MAGIC.len() + 2 * 4 + index_len).map_err(|_| too_large())?;
+
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        for (name, entry) in &self.entries {
+            let stored_len = entry.data.len() as u32;
+            writer.write_all(&(name.len() as u16).to_le_bytes())?;
+            writer.write_all(name.as_bytes())?;
+            writer.write_all(&[entry.compression.to_byte()])?;
+            writer.write_all(&offset.to_le_bytes())?;
+            writer.write_all(&stored_len.to_le_bytes())?;
+            writer.write_all(&entry.len.to_le_bytes())?;
+            offset = offset.checked_add(stored_len).ok_or_else(too_large)?;
+        }
+        for entry in self.entries.values() {
+            writer.write_all(&entry.data)?;
+        }
+        Ok(())
+    }
+}
+
+/// The `/` separated path `path` is stored under, which must be relative.
+fn archive_path(path: &Path) -> Option<String> {
+    let components = path
+        .components()
+        .map(|component| match component {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let name = components.join("/");
+    (!name.is_empty() && name.len() <= u16::MAX as usize).then_some(name)
+}
@@ -1,58 +1,195 @@
 use std::{
     fs::File,
+    future::Future,
     io::Read,
-    path::{Path, PathBuf},
-    pin::{pin, Pin},
+    path::{Component, Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll},
 };
 
-use bevy::asset::io::{file::FileAssetReader, AssetReader, AssetReaderError};
-use futures::AsyncRead;
+use bevy::asset::io::{AssetReader, AssetReaderError, PathStream};
+use futures::{channel::oneshot, ready, AsyncRead};
 use tracing::debug;
 
+use crate::io_thread::{lost_error, IoThread};
+
 /// Reads assets from the embedded romfs
-pub struct RomfsAssetReader;
-
-fn process_path(path: &Path) -> PathBuf {
-    assert!(
-        !path.starts_with("romfs:/"),
-        "path already starts with romfs, this is invalid"
-    );
-    PathBuf::from("romfs:/").join(path)
+///
+/// Asset paths are relative to the root, which is `romfs:/` by default. Any other
+/// directory can be used as the root with [`RomfsAssetReader::new`], e.g. `sdmc:/` for the
+/// SD card, or the `romfs` directory of the project when not on the 3ds.
+///
+/// Files are read on a dedicated thread, [`chunk_size`](RomfsAssetReader::with_chunk_size)
+/// bytes at a time, so loading assets doesn't block the thread polling the load.
+pub struct RomfsAssetReader {
+    root: PathBuf,
+    chunk_size: usize,
+}
+
+impl Default for RomfsAssetReader {
+    fn default() -> Self {
+        Self::new("romfs:/")
+    }
+}
+
+impl RomfsAssetReader {
+    /// The default number of bytes read from a file at a time.
+    pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            chunk_size: Self::DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    /// Sets the number of bytes read from a file at a time, larger chunks mean fewer trips
+    /// to the io thread but more memory per open asset.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "the chunk size can't be zero");
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// The directory asset paths are relative to.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The path of the asset at `path` under the root.
+    ///
+    /// Asset paths have to stay under the root, absolute paths replace the root when
+    /// joined and `..` can leave it, so those aren't found.
+    fn process_path(&self, path: &Path) -> Result<PathBuf, AssetReaderError> {
+        path_under_root(&self.root, path).ok_or_else(|| AssetReaderError::NotFound(path.to_owned()))
+    }
+}
+
+/// The path of the asset at `path` under `root`, if it stays under it.
+pub(crate) fn path_under_root(root: &Path, path: &Path) -> Option<PathBuf> {
+    let full_path = root.join(path);
+    let under_root = full_path.strip_prefix(root).is_ok_and(|relative| {
+        relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    });
+    if !under_root {
+        log::error!("asset path {:#?} leaves the root {:#?}", path, root);
+        return None;
+    }
+    Some(full_path)
+}
+
+fn reader_error(e: std::io::Error, p: PathBuf) -> AssetReaderError {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        // expected when overlaying, the asset server reports assets that aren't found
+        log::debug!("romfs path not found: {:#?}", p);
+        AssetReaderError::NotFound(p)
+    } else {
+        log::error!("failed to read from: {:#?}: {e}", p);
+        AssetReaderError::Io(e)
+    }
+}
+
+type ChunkRead = (File, Vec<u8>, std::io::Result<usize>);
+
+/// Reads a file a chunk at a time on the [`IoThread`].
+struct FileReader {
+    /// The file, unless it is on the io thread.
+    file: Option<File>,
+    chunk: Vec<u8>,
+    /// How much of the chunk has been read.
+    position: usize,
+    chunk_size: usize,
+    pending: Option<oneshot::Receiver<ChunkRead>>,
+}
+
+impl FileReader {
+    fn new(file: File, chunk_size: usize) -> Self {
+        Self {
+            file: Some(file),
+            chunk: Vec::new(),
+            position: 0,
+            chunk_size,
+            pending: None,
+        }
+    }
 }
 
-struct FileReader(File);
 impl AsyncRead for FileReader {
     fn poll_read(
-        self: std::pin::Pin<&mut Self>,
-        cx: &mut std::task::Context<'_>,
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
         buf: &mut [u8],
-    ) -> std::task::Poll<std::io::Result<usize>> {
-        std::task::Poll::Ready(self.get_mut().0.read(buf))
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            let unread = &this.chunk[this.position..];
+            if !unread.is_empty() {
+                let len = unread.len().min(buf.len());
+                buf[..len].copy_from_slice(&unread[..len]);
+                this.position += len;
+                return Poll::Ready(Ok(len));
+            }
+
+            if let Some(pending) = &mut this.pending {
+                let read = ready!(Pin::new(pending).poll(cx));
+                this.pending = None;
+                let Ok((file, mut chunk, result)) = read else {
+                    // the file went down with the panic
+                    return Poll::Ready(Err(lost_error()));
+                };
+                chunk.truncate(*result.as_ref().unwrap_or(&0));
+                this.file = Some(file);
+                this.chunk = chunk;
+                this.position = 0;
+                match result {
+                    // the end of the file
+                    Ok(0) => return Poll::Ready(Ok(0)),
+                    Ok(_) => continue,
+                    Err(e) => return Poll::Ready(Err(e)),
+                }
+            }
+
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            let Some(mut file) = this.file.take() else {
+                return Poll::Ready(Err(lost_error()));
+            };
+            let mut chunk = std::mem::take(&mut this.chunk);
+            this.position = 0;
+            let chunk_size = this.chunk_size;
+            this.pending = Some(IoThread::get().run(move || {
+                chunk.resize(chunk_size, 0);
+                let result = file.read(&mut chunk);
+                (file, chunk, result)
+            }));
+        }
     }
 }
 
 fn make_asset_reader<'a>(
-    p: &Path,
+    p: PathBuf,
+    chunk_size: usize,
 ) -> bevy::utils::BoxedFuture<
     'a,
     Result<Box<bevy::asset::io::Reader<'a>>, bevy::asset::io::AssetReaderError>,
 > {
-    let p = process_path(p);
     Box::pin(async move {
-        File::open(&p)
-            .map(|f| Box::new(FileReader(f)) as _)
-            .map_err(|e| {
-                if e.kind() == std::io::ErrorKind::NotFound {
-                    log::error!("romfs path not found: {:#?}", p);
-                    AssetReaderError::NotFound(p)
-                } else {
-                    log::error!("failed to read from: {:#?}: {e}", p);
-                    AssetReaderError::Io(e)
-                }
-            })
+        let file = IoThread::get()
+            .run_fallible(move || File::open(&p).map_err(|e| reader_error(e, p)))
+            .await?;
+        Ok(Box::new(FileReader::new(file, chunk_size)) as _)
     })
 }
 
+pub(crate) fn is_meta_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("meta"))
+}
+
 impl AssetReader for RomfsAssetReader {
     fn read<'a>(
         &'a self,
@@ -62,7 +199,9 @@ impl AssetReader for RomfsAssetReader {
         Result<Box<bevy::asset::io::Reader<'a>>, bevy::asset::io::AssetReaderError>,
     > {
         debug!("romfs read: {path:#?}");
-        make_asset_reader(path)
+        let full_path = self.process_path(path);
+        let chunk_size = self.chunk_size;
+        Box::pin(async move { make_asset_reader(full_path?, chunk_size).await })
     }
 
     fn read_meta<'a>(
@@ -72,7 +211,9 @@ impl AssetReader for RomfsAssetReader {
         'a,
         Result<Box<bevy::asset::io::Reader<'a>>, bevy::asset::io::AssetReaderError>,
     > {
-        make_asset_reader(&get_meta_path(path))
+        let full_path = self.process_path(&get_meta_path(path));
+        let chunk_size = self.chunk_size;
+        Box::pin(async move { make_asset_reader(full_path?, chunk_size).await })
     }
 
     fn read_directory<'a>(
@@ -82,19 +223,45 @@ impl AssetReader for RomfsAssetReader {
         'a,
         Result<Box<bevy::asset::io::PathStream>, bevy::asset::io::AssetReaderError>,
     > {
-        let path = process_path(path);
-        todo!()
+        let full_path = self.process_path(path);
+        let path = path.to_owned();
+        Box::pin(async move {
+            let full_path = full_path?;
+            let paths = IoThread::get()
+                .run_fallible(move || -> Result<_, AssetReaderError> {
+                    let entries =
+                        std::fs::read_dir(&full_path).map_err(|e| reader_error(e, full_path))?;
+                    // meta files aren't assets, and paths are kept relative to the root
+                    Ok(entries
+                        .filter_map(|entry| entry.ok())
+                        .map(|entry| path.join(entry.file_name()))
+                        .filter(|path| !is_meta_file(path))
+                        .collect::<Vec<_>>())
+                })
+                .await?;
+            Ok(Box::new(futures::stream::iter(paths)) as Box<PathStream>)
+        })
     }
 
     fn is_directory<'a>(
         &'a self,
         path: &'a std::path::Path,
     ) -> bevy::utils::BoxedFuture<'a, Result<bool, bevy::asset::io::AssetReaderError>> {
-        todo!()
+        let full_path = self.process_path(path);
+        Box::pin(async move {
+            let full_path = full_path?;
+            IoThread::get()
+                .run_fallible(move || {
+                    std::fs::metadata(&full_path)
+                        .map(|metadata| metadata.is_dir())
+                        .map_err(|e| reader_error(e, full_path))
+                })
+                .await
+        })
     }
 }
 
-fn get_meta_path(path: &Path) -> PathBuf {
+pub(crate) fn get_meta_path(path: &Path) -> PathBuf {
     let mut meta_path = path.to_path_buf();
     let mut extension = path
         .extension()
@@ -104,3 +271,82 @@ fn get_meta_path(path: &Path) -> PathBuf {
     meta_path.set_extension(extension);
     meta_path
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{is_directory, is_not_found, read, read_directory, read_meta, TempDir};
+
+    #[test]
+    fn assets_are_read_from_the_root() {
+        let dir = TempDir::new();
+        dir.write("sprite.png", b"sprite");
+        dir.write("sprite.png.meta", b"meta");
+        let reader = RomfsAssetReader::new(dir.path());
+
+        assert_eq!(read(&reader, "sprite.png").unwrap(), b"sprite");
+        assert_eq!(read_meta(&reader, "sprite.png").unwrap(), b"meta");
+        assert!(is_not_found(read(&reader, "missing.png")));
+        assert!(is_not_found(read_meta(&reader, "missing.png")));
+    }
+
+    #[test]
+    fn assets_can_be_under_a_directory_named_like_the_root() {
+        let dir = TempDir::new();
+        dir.write("romfs/romfs/level.ron", b"level");
+        let reader = RomfsAssetReader::new(dir.path().join("romfs"));
+
+        assert_eq!(read(&reader, "romfs/level.ron").unwrap(), b"level");
+        assert!(is_directory(&reader, "romfs").unwrap());
+    }
+
+    #[test]
+    fn files_are_read_a_chunk_at_a_time() {
+        let dir = TempDir::new();
+        let contents = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+        dir.write("data.bin", &contents);
+        let reader = RomfsAssetReader::new(dir.path()).with_chunk_size(7);
+
+        assert_eq!(read(&reader, "data.bin").unwrap(), contents);
+    }
+
+    #[test]
+    fn directories_list_assets_without_meta_files() {
+        let dir = TempDir::new();
+        dir.write("sprites/a.png", b"a");
+        dir.write("sprites/a.png.meta", b"meta");
+        dir.write("sprites/b.png", b"b");
+        dir.write("sprites/enemies/c.png", b"c");
+        let reader = RomfsAssetReader::new(dir.path());
+
+        assert_eq!(
+            read_directory(&reader, "sprites").unwrap(),
+            [
+                PathBuf::from("sprites/a.png"),
+                PathBuf::from("sprites/b.png"),
+                PathBuf::from("sprites/enemies"),
+            ]
+        );
+        assert!(is_directory(&reader, "sprites/enemies").unwrap());
+        assert!(!is_directory(&reader, "sprites/a.png").unwrap());
+        assert!(is_not_found(read_directory(&reader, "missing")));
+        assert!(is_not_found(is_directory(&reader, "missing")));
+    }
+
+    #[test]
+    fn paths_leaving_the_root_are_not_found() {
+        let dir = TempDir::new();
+        dir.write("secret.txt", b"secret");
+        dir.write("root/asset.txt", b"asset");
+        let reader = RomfsAssetReader::new(dir.path().join("root"));
+
+        assert!(is_not_found(read(&reader, "../secret.txt")));
+        assert!(is_not_found(read(
+            &reader,
+            dir.path().join("secret.txt").to_str().unwrap()
+        )));
+        assert!(is_not_found(read_directory(&reader, "..")));
+        assert!(is_not_found(is_directory(&reader, "..")));
+        assert_eq!(read(&reader, "./asset.txt").unwrap(), b"asset");
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use bevy::asset::io::{AssetReader, AssetReaderError};
+use futures::{executor::block_on, AsyncReadExt, StreamExt};
+
+use crate::io_thread::IoThread;
+
+/// A directory under the system temp dir, removed when dropped.
+pub(crate) struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub(crate) fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "bevy_3ds_romfs-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes `contents` to `path` under the directory, creating its parents.
+    pub(crate) fn write(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) {
+        let path = self.path.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Waits for everything sent to the io thread so far, e.g. the commit of a dropped writer.
+pub(crate) fn wait_for_io_thread() {
+    block_on(IoThread::get().run(|| ())).unwrap();
+}
+
+pub(crate) fn read(
+    reader: &(impl AssetReader + ?Sized),
+    path: &str,
+) -> Result<Vec<u8>, AssetReaderError> {
+    block_on(async {
+        let mut bytes = Vec::new();
+        reader
+            .read(Path::new(path))
+            .await?
+            .read_to_end(&mut bytes)
+            .await?;
+        Ok(bytes)
+    })
+}
+
+pub(crate) fn read_meta(
+    reader: &(impl AssetReader + ?Sized),
+    path: &str,
+) -> Result<Vec<u8>, AssetReaderError> {
+    block_on(async {
+        let mut bytes = Vec::new();
+        reader
+            .read_meta(Path::new(path))
+            .await?
+            .read_to_end(&mut bytes)
+            .await?;
+        Ok(bytes)
+    })
+}
+
+/// The sorted paths listed in the directory at `path`.
+pub(crate) fn read_directory(
+    reader: &(impl AssetReader + ?Sized),
+    path: &str,
+) -> Result<Vec<PathBuf>, AssetReaderError> {
+    block_on(async {
+        let mut paths = reader
+            .read_directory(Path::new(path))
+            .await?
+            .collect::<Vec<_>>()
+            .await;
+        paths.sort();
+        Ok(paths)
+    })
+}
+
+pub(crate) fn is_directory(
+    reader: &(impl AssetReader + ?Sized),
+    path: &str,
+) -> Result<bool, AssetReaderError> {
+    block_on(reader.is_directory(Path::new(path)))
+}
+
+pub(crate) fn is_not_found(result: Result<impl Sized, AssetReaderError>) -> bool {
+    matches!(result, Err(AssetReaderError::NotFound(_)))
+}
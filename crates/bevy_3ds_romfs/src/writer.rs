@@ -0,0 +1,608 @@
+use std::{
+    fs::File,
+    future::Future,
+    io::Write,
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bevy::asset::io::{AssetWriter, AssetWriterError, Writer};
+use bevy::utils::BoxedFuture;
+use futures::{channel::oneshot, ready, AsyncWrite};
+
+use crate::io_thread::{lost_error, IoThread};
+use crate::reader::{get_meta_path, path_under_root};
+
+/// Writes assets to the SD card
+///
+/// Asset paths are relative to the root, which is `sdmc:/` by default, and can't leave it.
+/// The root itself can't be removed, and is only cleared with
+/// [`allow_clearing_root`](SdAssetWriter::allow_clearing_root).
+///
+/// Assets are written to a temporary file next to them, which replaces the asset once the
+/// writer has been flushed and is dropped (or closed). Readers never see a half written
+/// asset, and a write that fails or is abandoned before flushing leaves the old asset as
+/// it was.
+///
+/// Files are written on the same thread the [`RomfsAssetReader`](crate::RomfsAssetReader)
+/// reads them on, so an asset read after it is written sees the new contents.
+pub struct SdAssetWriter {
+    root: PathBuf,
+    clear_root: bool,
+}
+
+impl Default for SdAssetWriter {
+    fn default() -> Self {
+        Self::new("sdmc:/")
+    }
+}
+
+impl SdAssetWriter {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            clear_root: false,
+        }
+    }
+
+    /// Lets [`remove_assets_in_directory`](AssetWriter::remove_assets_in_directory) clear
+    /// the whole root, which bevy's asset processor does to start its processed assets
+    /// over.
+    pub fn allow_clearing_root(mut self) -> Self {
+        self.clear_root = true;
+        self
+    }
+
+    /// The directory asset paths are relative to.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn full_path(&self, path: &Path) -> std::io::Result<PathBuf> {
+        path_under_root(&self.root, path).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{path:?} isn't under the asset root"),
+            )
+        })
+    }
+
+    /// The path of the directory at `path`, which can't be the root.
+    fn directory_path(&self, path: &Path) -> std::io::Result<PathBuf> {
+        let full_path = self.full_path(path)?;
+        if full_path == self.full_path(Path::new(""))? {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                "the asset root can't be removed",
+            ));
+        }
+        Ok(full_path)
+    }
+
+    fn write_owned(&self, path: PathBuf) -> BoxedFuture<'_, Result<Box<Writer>, AssetWriterError>> {
+        let full_path = self.full_path(&path);
+        Box::pin(async move {
+            let full_path = full_path?;
+            let writer = IoThread::get()
+                .run_fallible(move || AtomicFileWriter::create(full_path))
+                .await?;
+            Ok(Box::new(writer) as Box<Writer>)
+        })
+    }
+
+    fn remove_owned(&self, path: PathBuf) -> BoxedFuture<'_, Result<(), AssetWriterError>> {
+        let full_path = self.full_path(&path);
+        Box::pin(async move {
+            let full_path = full_path?;
+            IoThread::get()
+                .run_fallible(move || std::fs::remove_file(full_path))
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn rename_owned(
+        &self,
+        old_path: PathBuf,
+        new_path: PathBuf,
+    ) -> BoxedFuture<'_, Result<(), AssetWriterError>> {
+        let paths = self
+            .full_path(&old_path)
+            .and_then(|old_path| Ok((old_path, self.full_path(&new_path)?)));
+        Box::pin(async move {
+            let (old_path, new_path) = paths?;
+            IoThread::get()
+                .run_fallible(move || {
+                    if let Some(parent) = new_path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    replace(&old_path, &new_path)
+                })
+                .await?;
+            Ok(())
+        })
+    }
+}
+
+/// Moves `from` over `to`.
+fn replace(from: &Path, to: &Path) -> std::io::Result<()> {
+    match std::fs::rename(from, to) {
+        // the 3ds filesystem doesn't rename over existing files
+        Err(_) if to.exists() => {
+            std::fs::remove_file(to)?;
+            std::fs::rename(from, to)
+        }
+        result => result,
+    }
+}
+
+type FileOp = (File, std::io::Result<usize>);
+
+/// Writes an asset to a temporary file on the [`IoThread`], which replaces the asset once
+/// it is committed.
+struct AtomicFileWriter {
+    /// The file, unless it is on the io thread or the writer is closed.
+    file: Option<File>,
+    temp_path: PathBuf,
+    path: PathBuf,
+    /// The write or flush on the io thread.
+    pending: Option<oneshot::Receiver<FileOp>>,
+    /// Whether the pending operation is a flush.
+    flushing: bool,
+    committing: Option<oneshot::Receiver<std::io::Result<()>>>,
+    flushed: bool,
+    failed: bool,
+    closed: bool,
+}
+
+impl AtomicFileWriter {
+    fn create(path: PathBuf) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut temp_path = path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+        let file = File::create(&temp_path)?;
+        Ok(Self {
+            file: Some(file),
+            temp_path,
+            path,
+            pending: None,
+            flushing: false,
+            committing: None,
+            flushed: false,
+            failed: false,
+            closed: false,
+        })
+    }
+
+    /// Waits for the pending operation, `None` if there isn't one.
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<Option<std::io::Result<usize>>> {
+        let Some(pending) = &mut self.pending else {
+            return Poll::Ready(None);
+        };
+        let done = ready!(Pin::new(pending).poll(cx));
+        self.pending = None;
+        let result = match done {
+            Ok((file, result)) => {
+                self.file = Some(file);
+                result
+            }
+            // the file went down with the panic
+            Err(_) => Err(lost_error()),
+        };
+        self.failed |= result.is_err();
+        Poll::Ready(Some(result))
+    }
+
+    /// Sends `op` to the io thread with the file.
+    fn start(
+        &mut self,
+        op: impl FnOnce(&mut File) -> std::io::Result<usize> + Send + 'static,
+    ) -> std::io::Result<()> {
+        let Some(mut file) = self.file.take() else {
+            return Err(if self.closed {
+                std::io::ErrorKind::BrokenPipe.into()
+            } else {
+                lost_error()
+            });
+        };
+        self.pending = Some(IoThread::get().run(move || {
+            let result = op(&mut file);
+            (file, result)
+        }));
+        Ok(())
+    }
+
+    /// Closes the file, then moves it over the asset, or removes it if the asset failed
+    /// or was abandoned.
+    fn commit(&mut self, abandoned: bool) -> impl FnOnce() -> std::io::Result<()> + Send {
+        self.closed = true;
+        // a write still on the io thread closes the file itself, before this runs
+        self.pending = None;
+        let file = self.file.take();
+        let failed = self.failed || abandoned || file.is_none();
+        let (temp_path, path) = (self.temp_path.clone(), self.path.clone());
+        move || {
+            // the file has to be closed before it can be renamed
+            drop(file);
+            if failed {
+                std::fs::remove_file(&temp_path)?;
+                return Err(std::io::Error::other("a write to the asset failed"));
+            }
+            replace(&temp_path, &path).inspect_err(|_| {
+                let _ = std::fs::remove_file(&temp_path);
+            })
+        }
+    }
+}
+
+impl AsyncWrite for AtomicFileWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            let flushing = this.flushing;
+            match ready!(this.poll_pending(cx)) {
+                // the write this call was retried for
+                Some(result) if !flushing => return Poll::Ready(result),
+                Some(_) => this.flushing = false,
+                None => {}
+            }
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            let data = buf.to_vec();
+            this.flushed = false;
+            if let Err(e) = this.start(move |file| file.write_all(&data).map(|()| data.len())) {
+                return Poll::Ready(Err(e));
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            let flushing = this.flushing;
+            match ready!(this.poll_pending(cx)) {
+                Some(Err(e)) => {
+                    this.flushing = false;
+                    return Poll::Ready(Err(e));
+                }
+                Some(Ok(_)) if flushing => {
+                    this.flushing = false;
+                    this.flushed = true;
+                    return Poll::Ready(Ok(()));
+                }
+                _ => {}
+            }
+            if this.closed {
+                return Poll::Ready(Ok(()));
+            }
+            this.flushing = true;
+            if let Err(e) = this.start(|file| file.flush().map(|()| 0)) {
+                this.flushing = false;
+                return Poll::Ready(Err(e));
+            }
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(committing) = &mut this.committing {
+                let result = ready!(Pin::new(committing).poll(cx));
+                this.committing = None;
+                return Poll::Ready(result.unwrap_or_else(|_| Err(lost_error())));
+            }
+            if this.closed {
+                return Poll::Ready(Ok(()));
+            }
+            // a failed write is remembered, and fails the commit
+            ready!(this.poll_pending(cx));
+            let commit = this.commit(false);
+            this.committing = Some(IoThread::get().run(commit));
+        }
+    }
+}
+
+impl Drop for AtomicFileWriter {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+        let abandoned = !self.flushed || self.pending.is_some();
+        let commit = self.commit(abandoned);
+        let path = self.path.clone();
+        // nothing waits for it, the io thread runs it before anything sent after the drop
+        drop(IoThread::get().run(move || {
+            if let Err(e) = commit() {
+                log::error!("failed to write {:#?}: {e}", path);
+            }
+        }));
+    }
+}
+
+impl AssetWriter for SdAssetWriter {
+    fn write<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<Writer>, AssetWriterError>> {
+        self.write_owned(path.to_owned())
+    }
+
+    fn write_meta<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<Box<Writer>, AssetWriterError>> {
+        self.write_owned(get_meta_path(path))
+    }
+
+    fn remove<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<(), AssetWriterError>> {
+        self.remove_owned(path.to_owned())
+    }
+
+    fn remove_meta<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<(), AssetWriterError>> {
+        self.remove_owned(get_meta_path(path))
+    }
+
+    fn rename<'a>(
+        &'a self,
+        old_path: &'a Path,
+        new_path: &'a Path,
+    ) -> BoxedFuture<'a, Result<(), AssetWriterError>> {
+        self.rename_owned(old_path.to_owned(), new_path.to_owned())
+    }
+
+    fn rename_meta<'a>(
+        &'a self,
+        old_path: &'a Path,
+        new_path: &'a Path,
+    ) -> BoxedFuture<'a, Result<(), AssetWriterError>> {
+        self.rename_owned(get_meta_path(old_path), get_meta_path(new_path))
+    }
+
+    fn remove_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<(), AssetWriterError>> {
+        let full_path = self.directory_path(path);
+        Box::pin(async move {
+            let full_path = full_path?;
+            IoThread::get()
+                .run_fallible(move || std::fs::remove_dir_all(full_path))
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn remove_empty_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<(), AssetWriterError>> {
+        let full_path = self.directory_path(path);
+        Box::pin(async move {
+            let full_path = full_path?;
+            IoThread::get()
+                .run_fallible(move || std::fs::remove_dir(full_path))
+                .await?;
+            Ok(())
+        })
+    }
+
+    fn remove_assets_in_directory<'a>(
+        &'a self,
+        path: &'a Path,
+    ) -> BoxedFuture<'a, Result<(), AssetWriterError>> {
+        let full_path = if self.clear_root {
+            self.full_path(path)
+        } else {
+            self.directory_path(path)
+        };
+        Box::pin(async move {
+            let full_path = full_path?;
+            IoThread::get()
+                .run_fallible(move || {
+                    std::fs::remove_dir_all(&full_path)?;
+                    std::fs::create_dir_all(&full_path)
+                })
+                .await?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use futures::{executor::block_on, AsyncWriteExt};
+
+    use super::*;
+    use crate::test_util::{wait_for_io_thread, TempDir};
+
+    fn write_asset(writer: &SdAssetWriter, path: &str) -> Box<Writer> {
+        block_on(writer.write(Path::new(path))).unwrap()
+    }
+
+    fn error_kind(result: Result<impl Sized, AssetWriterError>) -> Option<ErrorKind> {
+        match result {
+            Err(AssetWriterError::Io(e)) => Some(e.kind()),
+            Ok(_) => None,
+        }
+    }
+
+    fn files(dir: &Path) -> Vec<PathBuf> {
+        let mut files = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path().strip_prefix(dir).unwrap().to_owned())
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn assets_are_only_visible_once_committed() {
+        let dir = TempDir::new();
+        let writer = SdAssetWriter::new(dir.path());
+
+        let mut asset = write_asset(&writer, "levels/1.ron");
+        block_on(asset.write_all(b"level")).unwrap();
+        block_on(asset.flush()).unwrap();
+        assert!(!dir.path().join("levels/1.ron").exists());
+
+        block_on(asset.close()).unwrap();
+        assert_eq!(
+            std::fs::read(dir.path().join("levels/1.ron")).unwrap(),
+            b"level"
+        );
+        assert_eq!(files(&dir.path().join("levels")), [PathBuf::from("1.ron")]);
+    }
+
+    #[test]
+    fn old_contents_are_replaced_on_commit() {
+        let dir = TempDir::new();
+        dir.write("level.ron", b"old");
+        dir.write("level.ron.meta", b"old meta");
+        let writer = SdAssetWriter::new(dir.path());
+
+        let mut asset = write_asset(&writer, "level.ron");
+        block_on(asset.write_all(b"new")).unwrap();
+        block_on(asset.flush()).unwrap();
+        assert_eq!(std::fs::read(dir.path().join("level.ron")).unwrap(), b"old");
+        // flushed writers commit when dropped
+        drop(asset);
+        wait_for_io_thread();
+        assert_eq!(std::fs::read(dir.path().join("level.ron")).unwrap(), b"new");
+
+        let mut meta = block_on(writer.write_meta(Path::new("level.ron"))).unwrap();
+        block_on(meta.write_all(b"new meta")).unwrap();
+        block_on(meta.close()).unwrap();
+        assert_eq!(
+            std::fs::read(dir.path().join("level.ron.meta")).unwrap(),
+            b"new meta"
+        );
+        assert_eq!(
+            files(dir.path()),
+            [PathBuf::from("level.ron"), PathBuf::from("level.ron.meta")]
+        );
+    }
+
+    #[test]
+    fn abandoned_writes_leave_no_temp_file() {
+        let dir = TempDir::new();
+        dir.write("level.ron", b"old");
+        let writer = SdAssetWriter::new(dir.path());
+
+        let mut asset = write_asset(&writer, "level.ron");
+        block_on(asset.write_all(b"half a lev")).unwrap();
+        drop(asset);
+        wait_for_io_thread();
+        assert_eq!(std::fs::read(dir.path().join("level.ron")).unwrap(), b"old");
+
+        drop(write_asset(&writer, "new.ron"));
+        wait_for_io_thread();
+        assert_eq!(files(dir.path()), [PathBuf::from("level.ron")]);
+    }
+
+    #[test]
+    fn assets_are_renamed_and_removed() {
+        let dir = TempDir::new();
+        dir.write("a.png", b"a");
+        dir.write("a.png.meta", b"a meta");
+        dir.write("b.png", b"b");
+        dir.write("sprites/c.png", b"c");
+        dir.write("sprites/enemies/d.png", b"d");
+        let writer = SdAssetWriter::new(dir.path());
+
+        block_on(writer.rename(Path::new("a.png"), Path::new("moved/a.png"))).unwrap();
+        block_on(writer.rename_meta(Path::new("a.png"), Path::new("moved/a.png"))).unwrap();
+        assert_eq!(
+            files(&dir.path().join("moved")),
+            [PathBuf::from("a.png"), PathBuf::from("a.png.meta")]
+        );
+        // over an existing asset
+        block_on(writer.rename(Path::new("b.png"), Path::new("moved/a.png"))).unwrap();
+        assert_eq!(std::fs::read(dir.path().join("moved/a.png")).unwrap(), b"b");
+
+        block_on(writer.remove(Path::new("moved/a.png"))).unwrap();
+        block_on(writer.remove_meta(Path::new("moved/a.png"))).unwrap();
+        assert!(files(&dir.path().join("moved")).is_empty());
+        assert_eq!(
+            error_kind(block_on(writer.remove(Path::new("moved/a.png")))),
+            Some(ErrorKind::NotFound)
+        );
+        block_on(writer.remove_empty_directory(Path::new("moved"))).unwrap();
+
+        block_on(writer.remove_assets_in_directory(Path::new("sprites"))).unwrap();
+        assert!(files(&dir.path().join("sprites")).is_empty());
+        block_on(writer.remove_directory(Path::new("sprites"))).unwrap();
+        assert!(files(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn paths_leaving_the_root_are_rejected() {
+        let dir = TempDir::new();
+        dir.write("secret.txt", b"secret");
+        dir.write("root/asset.txt", b"asset");
+        let writer = SdAssetWriter::new(dir.path().join("root"));
+        let secret = dir.path().join("secret.txt");
+
+        assert_eq!(
+            error_kind(block_on(writer.write(Path::new("../secret.txt")))),
+            Some(ErrorKind::NotFound)
+        );
+        assert_eq!(
+            error_kind(block_on(writer.write(&secret))),
+            Some(ErrorKind::NotFound)
+        );
+        assert_eq!(
+            error_kind(block_on(writer.remove(Path::new("../secret.txt")))),
+            Some(ErrorKind::NotFound)
+        );
+        assert_eq!(
+            error_kind(block_on(
+                writer.rename(Path::new("asset.txt"), Path::new("../secret.txt"))
+            )),
+            Some(ErrorKind::NotFound)
+        );
+        assert_eq!(
+            error_kind(block_on(
+                writer.rename(Path::new("../secret.txt"), Path::new("asset.txt"))
+            )),
+            Some(ErrorKind::NotFound)
+        );
+        for path in ["..", ""] {
+            assert!(error_kind(block_on(writer.remove_directory(Path::new(path)))).is_some());
+            assert!(error_kind(block_on(writer.remove_empty_directory(Path::new(path)))).is_some());
+            assert!(
+                error_kind(block_on(writer.remove_assets_in_directory(Path::new(path)))).is_some()
+            );
+        }
+
+        assert_eq!(std::fs::read(&secret).unwrap(), b"secret");
+        assert_eq!(
+            std::fs::read(dir.path().join("root/asset.txt")).unwrap(),
+            b"asset"
+        );
+    }
+
+    #[test]
+    fn the_root_is_only_cleared_when_allowed() {
+        let dir = TempDir::new();
+        dir.write("processed/a.png", b"a");
+        dir.write("processed/sprites/b.png", b"b");
+        let writer = SdAssetWriter::new(dir.path().join("processed")).allow_clearing_root();
+
+        block_on(writer.remove_assets_in_directory(Path::new(""))).unwrap();
+        assert!(files(&dir.path().join("processed")).is_empty());
+        assert!(error_kind(block_on(writer.remove_directory(Path::new("")))).is_some());
+        assert!(error_kind(block_on(writer.remove_assets_in_directory(Path::new("..")))).is_some());
+        assert_eq!(files(dir.path()), [PathBuf::from("processed")]);
+    }
+}
@@ -1,3 +1,4 @@
+use bevy::app::PluginGroup;
 use bevy::asset::AssetServer;
 use bevy::core_pipeline::core_3d::Camera3dBundle;
 use bevy::ecs::system::{Query, Res};
@@ -15,6 +16,7 @@ use bevy::{
     ecs::system::Commands,
 };
 use bevy_3ds_input::button::*;
+use bevy_3ds_input::timing::Chord3ds;
 use bevy_3ds_render::On3dsScreen;
 
 mod setup_logger;
@@ -33,7 +35,11 @@ fn main() {
     }
 
     let mut app = App::new();
-    app.add_plugins(bevy_3ds::DefaultPlugins);
+    // quit with Start, the shortcut is off by default
+    app.add_plugins(bevy_3ds::DefaultPlugins.set(bevy_3ds::Core3dsPlugin {
+        quit_chord: Some(Chord3ds::new([Button3dsType::Start])),
+        ..Default::default()
+    }));
     app.add_systems(Startup, setup);
 
     app.run();
@@ -1,5 +1,6 @@
 use std::f32::consts::PI;
 
+use bevy::app::PluginGroup;
 use bevy::app::Update;
 use bevy::asset::AssetServer;
 use bevy::core_pipeline::core_3d::Camera3dBundle;
@@ -23,6 +24,8 @@ use bevy::{
     ecs::system::Commands,
     hierarchy::BuildChildren,
 };
+use bevy_3ds_input::button::Button3dsType;
+use bevy_3ds_input::timing::Chord3ds;
 use bevy_3ds_render::{pending_render_system, CameraID, On3dsScreen, RenderOn};
 
 mod shims;
@@ -34,7 +37,11 @@ fn main() {
     let _romfs = ctru::services::romfs::RomFS::new().unwrap();
 
     let mut app = App::new();
-    app.add_plugins(bevy_3ds::DefaultPlugins);
+    // quit with Start, the shortcut is off by default
+    app.add_plugins(bevy_3ds::DefaultPlugins.set(bevy_3ds::Core3dsPlugin {
+        quit_chord: Some(Chord3ds::new([Button3dsType::Start])),
+        ..Default::default()
+    }));
     app.add_systems(Startup, setup);
     app.add_systems(Update, update);
     app.add_systems(Update, pending_render_system);
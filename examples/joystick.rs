@@ -1,5 +1,6 @@
 use std::f32::consts::PI;
 
+use bevy::app::PluginGroup;
 use bevy::app::Update;
 use bevy::asset::AssetServer;
 use bevy::core_pipeline::core_3d::{Camera3d, Camera3dBundle};
@@ -28,6 +29,8 @@ use bevy::{
     },
 };
 use bevy_3ds_input::axis::{Axis3ds, Axis3dsType};
+use bevy_3ds_input::button::Button3dsType;
+use bevy_3ds_input::timing::Chord3ds;
 
 mod setup_logger;
 mod shims;
@@ -41,7 +44,11 @@ fn main() {
     setup_logger::setup_logger().unwrap();
 
     let mut app = App::new();
-    app.add_plugins(bevy_3ds::DefaultPlugins);
+    // quit with Start, the shortcut is off by default
+    app.add_plugins(bevy_3ds::DefaultPlugins.set(bevy_3ds::Core3dsPlugin {
+        quit_chord: Some(Chord3ds::new([Button3dsType::Start])),
+        ..Default::default()
+    }));
     app.add_systems(Startup, setup);
     app.add_systems(Update, update);
 
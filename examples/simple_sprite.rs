@@ -10,11 +10,12 @@ use bevy::render::color::Color;
 use bevy::sprite::{Sprite, SpriteBundle};
 use bevy::transform::components::Transform;
 use bevy::{
-    app::{App, Startup, Update},
+    app::{App, PluginGroup, Startup, Update},
     core_pipeline::core_2d::Camera2dBundle,
     ecs::system::Commands,
 };
 use bevy_3ds_input::button::*;
+use bevy_3ds_input::timing::Chord3ds;
 
 use tracing::error;
 
@@ -24,7 +25,11 @@ fn main() {
     let _romfs = ctru::services::romfs::RomFS::new().unwrap();
 
     let mut app = App::new();
-    app.add_plugins(bevy_3ds::DefaultPlugins);
+    // quit with Start, the shortcut is off by default
+    app.add_plugins(bevy_3ds::DefaultPlugins.set(bevy_3ds::Core3dsPlugin {
+        quit_chord: Some(Chord3ds::new([Button3dsType::Start])),
+        ..Default::default()
+    }));
     app.add_systems(Startup, setup);
     app.add_systems(Update, pupdate);
 
@@ -14,6 +14,9 @@ use bevy::{
     transform::TransformPlugin,
     window::{Window, WindowPlugin, WindowResolution},
 };
+use bevy_3ds_input::gamepad::Gamepad3dsPlugin;
+use bevy_3ds_input::gesture::Gesture3dsPlugin;
+use bevy_3ds_input::timing::ButtonTiming3dsPlugin;
 use bevy_3ds_input::InputPlugin;
 #[cfg(feature = "pbr")]
 use bevy_3ds_pbr::Bevy3dsPbrPlugin;
@@ -32,10 +35,13 @@ impl PluginGroup for DefaultPlugins {
             .add(bevy::core::FrameCountPlugin)
             .add(bevy::time::TimePlugin)
             .add(bevy::app::ScheduleRunnerPlugin::default())
-            .add(Core3dsPlugin)
+            .add(Core3dsPlugin::default())
             .add(TransformPlugin)
             .add(HierarchyPlugin)
             .add(InputPlugin)
+            .add(Gamepad3dsPlugin)
+            .add(Gesture3dsPlugin)
+            .add(ButtonTiming3dsPlugin)
             //.add_plugins(romfs_assets::RomfsAssetPlugin)
             .add(WindowPlugin {
                 primary_window: Some(Window {
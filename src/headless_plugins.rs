@@ -0,0 +1,103 @@
+use crate::lifecycle::AppLifecycle3ds;
+use crate::system::System3dsSettings;
+
+use bevy::{
+    app::{App, Plugin, PluginGroup, PluginGroupBuilder},
+    asset::AssetPlugin,
+    hierarchy::HierarchyPlugin,
+    render::{mesh::MeshPlugin, texture::ImagePlugin},
+    scene::ScenePlugin,
+    transform::TransformPlugin,
+};
+use bevy_3ds_input::gamepad::Gamepad3dsPlugin;
+use bevy_3ds_input::gesture::Gesture3dsPlugin;
+use bevy_3ds_input::timing::ButtonTiming3dsPlugin;
+use bevy_3ds_input::InputPlugin;
+
+/// The plugins of `DefaultPlugins` that don't render, for running the game off the 3ds,
+/// e.g. in integration tests.
+///
+/// It uses bevy's schedule runner instead of the APT loop, and input is read from a
+/// [`MockInputSource3ds`](bevy_3ds_input::source::MockInputSource3ds) (and the host
+/// keyboard) unless another [`Input3dsSource`](bevy_3ds_input::source::Input3dsSource)
+/// is inserted first. Assets are loaded from the `romfs/assets` directory, so the same
+/// paths work as on the 3ds. [`AppLifecycle3ds`] events are never sent.
+///
+/// ## Usage
+///
+/// Build without the 3ds only features, e.g.
+/// `cargo test --no-default-features --features gltf --tests`.
+/// `App::update` doesn't finish setting up the plugins, so call `App::finish` and
+/// `App::cleanup` first when driving the app by hand:
+///
+/// ```
+/// use bevy::app::App;
+/// use bevy_3ds::input::source::{Input3dsSource, MockInputSource3ds};
+/// use bevy_3ds::HeadlessPlugins;
+///
+/// let mock = MockInputSource3ds::default();
+/// let mut app = App::new();
+/// app.insert_resource(Input3dsSource::new(mock.clone()))
+///     .add_plugins(HeadlessPlugins);
+/// app.finish();
+/// app.cleanup();
+/// app.update();
+/// ```
+pub struct HeadlessPlugins;
+
+impl PluginGroup for HeadlessPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        let mut group = PluginGroupBuilder::start::<Self>();
+
+        group = group
+            .add(bevy::core::TaskPoolPlugin::default())
+            .add(bevy::core::TypeRegistrationPlugin)
+            .add(bevy::core::FrameCountPlugin)
+            .add(bevy::time::TimePlugin)
+            .add(bevy::app::ScheduleRunnerPlugin::default())
+            .add(HeadlessCore3dsPlugin)
+            .add(TransformPlugin)
+            .add(HierarchyPlugin)
+            .add(InputPlugin)
+            .add(Gamepad3dsPlugin)
+            .add(Gesture3dsPlugin)
+            .add(ButtonTiming3dsPlugin)
+            .add(AssetPlugin {
+                file_path: "romfs/assets".to_owned(),
+                ..Default::default()
+            })
+            // the mesh and image assets, without the render world
+            .add(MeshPlugin)
+            .add(ImagePlugin::default())
+            .add(ScenePlugin);
+        #[cfg(feature = "gltf")]
+        {
+            group = group
+                .add(GltfMaterials)
+                .add(bevy::gltf::GltfPlugin::default());
+        }
+        group
+    }
+}
+
+/// Stands in for `Core3dsPlugin` off the 3ds.
+struct HeadlessCore3dsPlugin;
+
+impl Plugin for HeadlessCore3dsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AppLifecycle3ds>()
+            .init_resource::<System3dsSettings>();
+    }
+}
+
+/// The materials glTF files are loaded into, without the pbr renderer.
+#[cfg(feature = "gltf")]
+struct GltfMaterials;
+
+#[cfg(feature = "gltf")]
+impl Plugin for GltfMaterials {
+    fn build(&self, app: &mut App) {
+        use bevy::asset::AssetApp;
+        app.init_asset::<bevy::pbr::StandardMaterial>();
+    }
+}
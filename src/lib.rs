@@ -1,10 +1,17 @@
 #![feature(allocator_api)]
+#[cfg(target_os = "horizon")]
 use bevy::{
-    app::{App, Plugin, PluginsState},
-    input::Input,
+    app::{App, AppExit, Last, Plugin, PluginsState, PreUpdate},
+    ecs::{
+        event::{EventWriter, Events, ManualEventReader},
+        schedule::IntoSystemConfigs,
+        system::Res,
+    },
+    input::{Input, InputSystem},
     tasks::tick_global_task_pools_on_main_thread,
 };
 
+#[cfg(target_os = "horizon")]
 mod plugins;
 
 #[cfg(feature = "render")]
@@ -21,21 +28,80 @@ pub mod input {
     pub use bevy_3ds_input::*;
 }
 
+#[cfg(target_os = "horizon")]
 mod default_plugins;
+mod headless_plugins;
+pub mod lifecycle;
+pub mod system;
 
-use bevy_3ds_input::button::{Button3ds, Button3dsType};
+#[cfg(target_os = "horizon")]
+use bevy_3ds_input::button::Button3ds;
+#[cfg(target_os = "horizon")]
+use bevy_3ds_input::timing::Chord3ds;
+#[cfg(target_os = "horizon")]
 use ctru::prelude::*;
+#[cfg(target_os = "horizon")]
 pub use default_plugins::DefaultPlugins;
+pub use headless_plugins::HeadlessPlugins;
+#[cfg(target_os = "horizon")]
+use lifecycle::{AppLifecycle3ds, AptLifecycle};
+#[cfg(target_os = "horizon")]
+use system::{apply_system_settings, System3dsSettings};
+#[cfg(all(target_os = "horizon", feature = "multi-threaded"))]
+use system::{run_task_pool_on_system_core, SystemCoreWorkers};
 
-pub struct Core3dsPlugin;
+/// Sets up the app to run on the 3ds.
+///
+/// The app runs until it sends [`AppExit`] or is closed from the HOME menu, after which
+/// one last update is run so cleanup systems can react to the exit. Closing from the HOME
+/// menu sends an [`AppExit`] for that update.
+#[cfg(target_os = "horizon")]
+#[derive(Default, Clone)]
+pub struct Core3dsPlugin {
+    /// Buttons that quit the app when pressed together, e.g. L+R+Start. `None` disables
+    /// the shortcut.
+    pub quit_chord: Option<Chord3ds>,
+    /// Runs bevy's `ComputeTaskPool` on the system core too, with these workers, see
+    /// [`run_task_pool_on_system_core`]. `None` leaves the system core to the system.
+    #[cfg(feature = "multi-threaded")]
+    pub system_core: Option<SystemCoreWorkers>,
+}
 
+#[cfg(target_os = "horizon")]
 impl Plugin for Core3dsPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         std::env::set_var("BEVY_ASSET_ROOT", "romfs:/");
-        app.set_runner(app_runner);
+        app.add_event::<AppLifecycle3ds>()
+            .init_resource::<System3dsSettings>()
+            .add_systems(Last, apply_system_settings)
+            .set_runner(app_runner);
+
+        if let Some(chord) = self.quit_chord.clone() {
+            app.add_systems(
+                PreUpdate,
+                (move |buttons: Res<Input<Button3ds>>, mut exit: EventWriter<AppExit>| {
+                    if chord.just_pressed(&buttons) {
+                        exit.send(AppExit);
+                    }
+                })
+                .after(InputSystem),
+            );
+        }
+    }
+
+    #[cfg(feature = "multi-threaded")]
+    fn finish(&self, _app: &mut App) {
+        // after `TaskPoolPlugin` has made the pool
+        if let Some(workers) = self.system_core {
+            let pool = bevy::tasks::ComputeTaskPool::get_or_init(Default::default);
+            if let Err(e) = run_task_pool_on_system_core(pool, workers) {
+                log::error!("failed to start the system core workers: {e}");
+            }
+        }
     }
 }
 
+#[cfg(target_os = "horizon")]
 fn app_runner(mut app: App) {
     if app.plugins_state() == PluginsState::Ready {
         app.finish();
@@ -43,16 +109,10 @@ fn app_runner(mut app: App) {
     }
 
     let apt = Apt::new().unwrap();
+    let lifecycle = AptLifecycle::hook();
+    let mut app_exit_reader = ManualEventReader::<AppExit>::default();
+    let mut exited = false;
     while apt.main_loop() {
-        let buttons = app
-            .world
-            .get_resource::<Input<Button3ds>>()
-            .expect("Input<_3dsButton> resource not found");
-
-        if buttons.pressed(Button3ds::new(Button3dsType::Start)) {
-            break;
-        }
-
         if app.plugins_state() != PluginsState::Cleaned {
             if app.plugins_state() != PluginsState::Ready {
                 tick_global_task_pools_on_main_thread();
@@ -63,7 +123,23 @@ fn app_runner(mut app: App) {
         }
 
         if app.plugins_state() == PluginsState::Cleaned {
-            app.update();
+            lifecycle.update(&mut app);
+
+            if let Some(app_exit_events) = app.world.get_resource::<Events<AppExit>>() {
+                if app_exit_reader.read(app_exit_events).last().is_some() {
+                    exited = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    // lets systems reading AppExit clean up, the app didn't send it when it was closed
+    // from the HOME menu
+    if app.plugins_state() == PluginsState::Cleaned {
+        if !exited {
+            app.world.send_event(AppExit);
         }
+        app.update();
     }
 }
@@ -0,0 +1,223 @@
+#[cfg(target_os = "horizon")]
+use std::ffi::c_void;
+#[cfg(any(target_os = "horizon", test))]
+use std::sync::Mutex;
+
+#[cfg(any(target_os = "horizon", test))]
+use bevy::app::App;
+use bevy::ecs::event::Event;
+#[cfg(any(target_os = "horizon", test))]
+use bevy::ecs::event::Events;
+#[cfg(any(target_os = "horizon", test))]
+use bevy::time::{Time, Virtual};
+
+/// Sent when the system takes the app away, and when it gives it back.
+///
+/// The app doesn't run while it is suspended or asleep, so `Suspending` usually arrives
+/// together with `Resumed` on the first update afterwards. [`Time<Virtual>`] doesn't
+/// advance over the update after `Resumed` or `Woken`, so gameplay doesn't jump by the
+/// time spent away.
+#[derive(Event, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AppLifecycle3ds {
+    /// The HOME menu or another applet is taking over.
+    Suspending,
+    /// The app is back from the HOME menu.
+    Resumed,
+    /// The console is going to sleep, e.g. because the lid was closed.
+    Sleeping,
+    /// The console woke up from sleep.
+    Woken,
+}
+
+/// The [`AppLifecycle3ds`] events sent since the last update.
+#[cfg(any(target_os = "horizon", test))]
+#[derive(Default)]
+pub(crate) struct LifecycleQueue {
+    pending: Mutex<Vec<AppLifecycle3ds>>,
+}
+
+#[cfg(any(target_os = "horizon", test))]
+impl LifecycleQueue {
+    pub(crate) fn push(&self, event: AppLifecycle3ds) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.push(event);
+        }
+    }
+
+    /// Sends the [`AppLifecycle3ds`] events collected since the last call, returns
+    /// whether the app was away in the meantime.
+    pub(crate) fn flush(&self, app: &mut App) -> bool {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+        let away = pending
+            .iter()
+            .any(|event| matches!(event, AppLifecycle3ds::Resumed | AppLifecycle3ds::Woken));
+        if let Some(mut events) = app.world.get_resource_mut::<Events<AppLifecycle3ds>>() {
+            events.extend(pending);
+        }
+        away
+    }
+
+    /// Runs one update, without advancing [`Time<Virtual>`] if the app was away.
+    pub(crate) fn update(&self, app: &mut App) {
+        let away = self.flush(app);
+        let pause = away
+            && app
+                .world
+                .get_resource::<Time<Virtual>>()
+                .is_some_and(|time| !time.is_paused());
+
+        if pause {
+            app.world.resource_mut::<Time<Virtual>>().pause();
+        }
+        app.update();
+        if pause {
+            app.world.resource_mut::<Time<Virtual>>().unpause();
+        }
+    }
+}
+
+/// Collects the APT hook callbacks for the runner, unhooks when dropped.
+#[cfg(target_os = "horizon")]
+pub(crate) struct AptLifecycle {
+    cookie: ctru_sys::aptHookCookie,
+    queue: LifecycleQueue,
+}
+
+#[cfg(target_os = "horizon")]
+impl AptLifecycle {
+    // boxed so the cookie and the callback parameter don't move while hooked
+    pub(crate) fn hook() -> Box<Self> {
+        let mut lifecycle = Box::new(Self {
+            cookie: unsafe { std::mem::zeroed() },
+            queue: LifecycleQueue::default(),
+        });
+        let param = &lifecycle.queue as *const LifecycleQueue as *mut c_void;
+        unsafe { ctru_sys::aptHook(&mut lifecycle.cookie, Some(apt_hook_callback), param) };
+        lifecycle
+    }
+
+    /// Runs one update, see [`LifecycleQueue::update`].
+    pub(crate) fn update(&self, app: &mut App) {
+        self.queue.update(app);
+    }
+}
+
+#[cfg(target_os = "horizon")]
+impl Drop for AptLifecycle {
+    fn drop(&mut self) {
+        unsafe { ctru_sys::aptUnhook(&mut self.cookie) };
+    }
+}
+
+// called by libctru, from the main thread for suspends and from the APT thread for sleep
+#[cfg(target_os = "horizon")]
+unsafe extern "C" fn apt_hook_callback(hook: ctru_sys::APT_HookType, param: *mut c_void) {
+    let event = match hook {
+        ctru_sys::APTHOOK_ONSUSPEND => AppLifecycle3ds::Suspending,
+        ctru_sys::APTHOOK_ONRESTORE => AppLifecycle3ds::Resumed,
+        ctru_sys::APTHOOK_ONSLEEP => AppLifecycle3ds::Sleeping,
+        ctru_sys::APTHOOK_ONWAKEUP => AppLifecycle3ds::Woken,
+        _ => return,
+    };
+    (*(param as *const LifecycleQueue)).push(event);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::event::ManualEventReader;
+    use bevy::time::{TimePlugin, TimeUpdateStrategy};
+
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(16);
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(TimePlugin)
+            .add_event::<AppLifecycle3ds>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME));
+        app
+    }
+
+    /// Runs an update that took `delta` of real time.
+    fn update(app: &mut App, queue: &LifecycleQueue, delta: Duration) -> Duration {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(delta));
+        queue.update(app);
+        app.world.resource::<Time<Virtual>>().delta()
+    }
+
+    #[test]
+    fn events_are_sent_in_order_on_the_next_update() {
+        let mut app = app();
+        let queue = LifecycleQueue::default();
+        let mut reader = ManualEventReader::<AppLifecycle3ds>::default();
+        let mut read = |app: &App| -> Vec<_> {
+            let events = app.world.resource::<Events<AppLifecycle3ds>>();
+            reader.read(events).copied().collect()
+        };
+
+        queue.push(AppLifecycle3ds::Suspending);
+        queue.push(AppLifecycle3ds::Resumed);
+        queue.push(AppLifecycle3ds::Sleeping);
+        queue.push(AppLifecycle3ds::Woken);
+        assert!(read(&app).is_empty());
+        queue.update(&mut app);
+        assert_eq!(
+            read(&app),
+            vec![
+                AppLifecycle3ds::Suspending,
+                AppLifecycle3ds::Resumed,
+                AppLifecycle3ds::Sleeping,
+                AppLifecycle3ds::Woken
+            ]
+        );
+
+        queue.update(&mut app);
+        assert!(read(&app).is_empty());
+    }
+
+    #[test]
+    fn time_doesnt_jump_after_being_away() {
+        let mut app = app();
+        let queue = LifecycleQueue::default();
+        update(&mut app, &queue, FRAME);
+        assert_eq!(update(&mut app, &queue, FRAME), FRAME);
+
+        queue.push(AppLifecycle3ds::Suspending);
+        queue.push(AppLifecycle3ds::Resumed);
+        let elapsed = app.world.resource::<Time<Virtual>>().elapsed();
+        assert_eq!(
+            update(&mut app, &queue, Duration::from_secs(60)),
+            Duration::ZERO
+        );
+        assert_eq!(app.world.resource::<Time<Virtual>>().elapsed(), elapsed);
+        assert!(!app.world.resource::<Time<Virtual>>().is_paused());
+        assert_eq!(update(&mut app, &queue, FRAME), FRAME);
+
+        queue.push(AppLifecycle3ds::Sleeping);
+        queue.push(AppLifecycle3ds::Woken);
+        assert_eq!(
+            update(&mut app, &queue, Duration::from_secs(60)),
+            Duration::ZERO
+        );
+        assert_eq!(update(&mut app, &queue, FRAME), FRAME);
+
+        // the app going away without coming back yet is a normal update
+        queue.push(AppLifecycle3ds::Suspending);
+        assert_eq!(update(&mut app, &queue, FRAME), FRAME);
+    }
+
+    #[test]
+    fn paused_time_stays_paused() {
+        let mut app = app();
+        let queue = LifecycleQueue::default();
+        update(&mut app, &queue, FRAME);
+        app.world.resource_mut::<Time<Virtual>>().pause();
+
+        queue.push(AppLifecycle3ds::Resumed);
+        update(&mut app, &queue, Duration::from_secs(60));
+        assert!(app.world.resource::<Time<Virtual>>().is_paused());
+    }
+}
@@ -0,0 +1,240 @@
+#[cfg(target_os = "horizon")]
+use std::any::Any;
+#[cfg(target_os = "horizon")]
+use std::ffi::c_void;
+#[cfg(target_os = "horizon")]
+use std::mem::MaybeUninit;
+#[cfg(target_os = "horizon")]
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+#[cfg(target_os = "horizon")]
+use bevy::ecs::change_detection::DetectChanges;
+#[cfg(target_os = "horizon")]
+use bevy::ecs::system::Res;
+use bevy::ecs::system::Resource;
+#[cfg(feature = "multi-threaded")]
+use bevy::tasks::TaskPool;
+
+/// Settings for how the system treats the app, changes are applied at the end of the frame.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct System3dsSettings {
+    /// Whether the console may go to sleep when the lid is closed.
+    pub allow_sleep: bool,
+    /// Whether to run at 804 MHz with the extra L2 cache, does nothing on an Old 3DS.
+    pub new_3ds_speedup: bool,
+}
+
+impl Default for System3dsSettings {
+    fn default() -> Self {
+        Self {
+            allow_sleep: true,
+            new_3ds_speedup: false,
+        }
+    }
+}
+
+#[cfg(target_os = "horizon")]
+pub(crate) fn apply_system_settings(settings: Res<System3dsSettings>) {
+    if settings.is_changed() {
+        unsafe {
+            ctru_sys::aptSetSleepAllowed(settings.allow_sleep);
+            ctru_sys::osSetSpeedupEnable(settings.new_3ds_speedup);
+        }
+    }
+}
+
+/// Workers on the system core (core 1) for bevy's [`ComputeTaskPool`], see
+/// [`run_task_pool_on_system_core`].
+///
+/// [`ComputeTaskPool`]: bevy::tasks::ComputeTaskPool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SystemCoreWorkers {
+    /// The share of the system core's time the app may use, in percent.
+    ///
+    /// The system keeps the rest for itself, the 3ds accepts values from 5 to 89.
+    pub time_share: u32,
+    /// The number of worker threads, at least one.
+    pub threads: usize,
+}
+
+impl Default for SystemCoreWorkers {
+    fn default() -> Self {
+        Self {
+            time_share: 30,
+            threads: 1,
+        }
+    }
+}
+
+#[cfg(target_os = "horizon")]
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Gives the app `workers.time_share` percent of the system core (core 1) and starts
+/// threads there that run `pool`'s tasks alongside its own threads.
+///
+/// Threads can't be moved between cores after they are created on the 3ds, and bevy
+/// creates its pools' threads on the app core, so the workers join the pool from the
+/// system core instead. [`Core3dsPlugin::system_core`](crate::Core3dsPlugin::system_core)
+/// does this for the [`ComputeTaskPool`], which runs `par_iter` and the multi-threaded
+/// schedule executor. The app core threads are still set with `TaskPoolPlugin`.
+///
+/// Fails without starting anything if the time share is out of range or there are no
+/// threads. Off the 3ds the workers are normal threads.
+///
+/// [`ComputeTaskPool`]: bevy::tasks::ComputeTaskPool
+#[cfg(feature = "multi-threaded")]
+pub fn run_task_pool_on_system_core(
+    pool: &'static TaskPool,
+    workers: SystemCoreWorkers,
+) -> std::io::Result<()> {
+    if workers.threads == 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "the system core workers need at least one thread",
+        ));
+    }
+    set_time_share(workers.time_share)?;
+
+    for _ in 0..workers.threads {
+        spawn_on_system_core(Box::new(move || {
+            // the thread runs the pool's tasks until the scope's own are done, this one
+            // never is
+            pool.scope(|scope| scope.spawn(std::future::pending::<()>()));
+        }))?;
+    }
+    Ok(())
+}
+
+/// Gives the app `time_share` percent of the system core (core 1), from 5 to 89.
+///
+/// [`run_task_pool_on_system_core`] does this, call it directly to only run threads started
+/// with [`spawn_on_system_core`]. Off the 3ds this only checks the range.
+pub fn set_time_share(time_share: u32) -> std::io::Result<()> {
+    if !(5..=89).contains(&time_share) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("the system core time share must be from 5 to 89, not {time_share}"),
+        ));
+    }
+    #[cfg(target_os = "horizon")]
+    apply_time_share(time_share)?;
+    Ok(())
+}
+
+#[cfg(target_os = "horizon")]
+fn apply_time_share(time_share: u32) -> std::io::Result<()> {
+    let result = unsafe { ctru_sys::APT_SetAppCpuTimeLimit(time_share) };
+    // negative results are errors
+    if result < 0 {
+        return Err(std::io::Error::other(format!(
+            "failed to set the system core time limit: {result:#x}"
+        )));
+    }
+    Ok(())
+}
+
+/// Runs `f` on a new thread on the system core (core 1).
+///
+/// The app needs time on that core, see [`set_time_share`] or
+/// [`Core3dsPlugin::system_core`](crate::Core3dsPlugin::system_core). This can start the
+/// render thread of `PipelinedRendering3dsPlugin`.
+#[cfg(target_os = "horizon")]
+pub fn spawn_on_system_core(f: Box<dyn FnOnce() + Send>) -> std::io::Result<()> {
+    extern "C" fn start(arg: *mut c_void) -> *mut c_void {
+        let f = unsafe { Box::from_raw(arg as *mut Job) };
+        // unwinding out of an extern "C" fn aborts
+        let _: Result<(), Box<dyn Any + Send>> = catch_unwind(AssertUnwindSafe(f));
+        std::ptr::null_mut()
+    }
+
+    unsafe {
+        let mut attr = MaybeUninit::uninit();
+        libc::pthread_attr_init(attr.as_mut_ptr());
+        libc::pthread_attr_setprocessorid_np(attr.as_mut_ptr(), 1);
+
+        let arg = Box::into_raw(Box::new(f));
+        let mut thread = MaybeUninit::uninit();
+        let result = libc::pthread_create(thread.as_mut_ptr(), attr.as_ptr(), start, arg.cast());
+        libc::pthread_attr_destroy(attr.as_mut_ptr());
+
+        if result != 0 {
+            drop(Box::from_raw(arg));
+            return Err(std::io::Error::from_raw_os_error(result));
+        }
+        libc::pthread_detach(thread.assume_init());
+    }
+    Ok(())
+}
+
+/// Runs `f` on a new thread, there is no system core to run it on off the 3ds.
+#[cfg(not(target_os = "horizon"))]
+pub fn spawn_on_system_core(f: Box<dyn FnOnce() + Send>) -> std::io::Result<()> {
+    std::thread::Builder::new()
+        .name("system core".to_owned())
+        .spawn(f)
+        .map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "multi-threaded")]
+    #[test]
+    fn pool_tasks_run_on_the_system_core_workers() {
+        // without threads of its own, only the workers run the pool's tasks
+        let pool = Box::leak(Box::new(
+            bevy::tasks::TaskPoolBuilder::new().num_threads(0).build(),
+        ));
+        let workers = SystemCoreWorkers {
+            threads: 2,
+            ..Default::default()
+        };
+        run_task_pool_on_system_core(pool, workers).unwrap();
+
+        let tasks = (0..8)
+            .map(|i| {
+                pool.spawn(async move { (i, std::thread::current().name().map(str::to_owned)) })
+            })
+            .collect::<Vec<_>>();
+        for (i, task) in tasks.into_iter().enumerate() {
+            let (value, thread) = bevy::tasks::block_on(task);
+            assert_eq!(value, i);
+            assert_eq!(thread.as_deref(), Some("system core"));
+        }
+    }
+
+    #[cfg(feature = "multi-threaded")]
+    #[test]
+    fn invalid_workers_are_rejected() {
+        let pool = Box::leak(Box::new(
+            bevy::tasks::TaskPoolBuilder::new().num_threads(0).build(),
+        ));
+        for workers in [
+            SystemCoreWorkers {
+                threads: 0,
+                ..Default::default()
+            },
+            SystemCoreWorkers {
+                time_share: 4,
+                ..Default::default()
+            },
+            SystemCoreWorkers {
+                time_share: 90,
+                ..Default::default()
+            },
+        ] {
+            let error = run_task_pool_on_system_core(pool, workers).err().unwrap();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn time_shares_are_checked() {
+        assert!(set_time_share(5).is_ok() && set_time_share(89).is_ok());
+        for time_share in [0, 4, 90] {
+            let error = set_time_share(time_share).err().unwrap();
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        }
+    }
+}